@@ -9,7 +9,7 @@ const SearchBar = ({setGeneId}) => {
     const searchText = inputRef.current.value;
 
     try {
-      const response = await fetch(`http://127.0.0.1:8000/search/${searchText}`);
+      const response = await fetch(`http://127.0.0.1:8000/search/${searchText}?summary=true`);
       if (response.ok) {
         const result = await response.json();
         console.log("Recieved: ", result);
@@ -33,11 +33,21 @@ const SearchBar = ({setGeneId}) => {
         <button onClick={handleSearch}>Buscar</button>
       </div>
       <div className="item-list">
-        {searchResults.map((pair, index) => (
-          <button key={index} onClick={() => handleItemClick(pair[0])}>
-            {pair[1]}
-          </button>
-        ))}
+        {searchResults.map((item, index) => {
+          // `published_mean` y `mean` son null si el gen no tiene media publicada ni
+          // predicciones.
+          const mean = item.summary && (item.summary.published_mean ?? item.summary.mean);
+          return (
+            <button key={index} onClick={() => handleItemClick(item.uniprot_id)}>
+              {item.gene}
+              {mean != null && (
+                <span className="gene-mean">
+                  {' '}{mean.toFixed(3)}
+                </span>
+              )}
+            </button>
+          );
+        })}
       </div>
     </div>
   );
@@ -26,15 +26,16 @@ const VariantInput = ({id}) => {
       const apiUrl = `http://127.0.0.1:8000/variants/${id}/${parsedText}`;
   
       fetch(apiUrl)
-        .then((response) => {
-          if (!response.ok) {
-            throw new Error('HTTP request failed');
-          }
-          return response.text();
-        })
+        .then((response) => response.json())
         .then((data) => {
           console.log('Received data:', data);
-          setReceivedText(data)
+          if (data.error) {
+            setReceivedText(data.message);
+          } else if (data.class === 'synonymous') {
+            setReceivedText('Silenciosa');
+          } else {
+            setReceivedText(`${data.class}: ${data.score}`);
+          }
         })
         .catch((error) => {
           console.error('Error fetching data:', error);
@@ -1,7 +1,8 @@
 use std::str::FromStr;
 
-use serde::{Serialize, Deserialize};
+use serde::{Deserialize, Serialize};
 
+/// Los 20 aminoácidos estándar.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum AminoAcid {
     Alanine,
@@ -26,19 +27,49 @@ pub enum AminoAcid {
     Valine,
 }
 
-
-
 impl AminoAcid {
+    /// Los 20 aminoácidos, en el orden de [`AminoAcid::index`].
+    pub const ALL: [AminoAcid; 20] = [
+        AminoAcid::Alanine,
+        AminoAcid::Arginine,
+        AminoAcid::Asparagine,
+        AminoAcid::AsparticAcid,
+        AminoAcid::Cysteine,
+        AminoAcid::GlutamicAcid,
+        AminoAcid::Glutamine,
+        AminoAcid::Glycine,
+        AminoAcid::Histidine,
+        AminoAcid::Isoleucine,
+        AminoAcid::Leucine,
+        AminoAcid::Lysine,
+        AminoAcid::Methionine,
+        AminoAcid::Phenylalanine,
+        AminoAcid::Proline,
+        AminoAcid::Serine,
+        AminoAcid::Threonine,
+        AminoAcid::Tryptophan,
+        AminoAcid::Tyrosine,
+        AminoAcid::Valine,
+    ];
+
+    /// Código de una letra de cada aminoácido.
     pub const SINGLE_LETTER_CODE: AminoAcidMap<char> = AminoAcidMap([
         'A', 'R', 'N', 'D', 'C', 'E', 'Q', 'G', 'H', 'I', 'L', 'K', 'M', 'F', 'P', 'S', 'T', 'W',
         'Y', 'V',
     ]);
 
+    /// Código de tres letras de cada aminoácido, en mayúsculas.
     pub const THREE_LETTER_CODE: AminoAcidMap<&'static str> = AminoAcidMap([
         "ALA", "ARG", "ASN", "ASP", "CYS", "GLU", "GLN", "GLY", "HIS", "ILE", "LEU", "LYS", "MET",
         "PHE", "PRO", "SER", "THR", "TRP", "TYR", "VAL",
     ]);
 
+    /// Código de tres letras de cada aminoácido como se escribe en HGVS (`Arg`).
+    pub const HGVS_CODE: AminoAcidMap<&'static str> = AminoAcidMap([
+        "Ala", "Arg", "Asn", "Asp", "Cys", "Glu", "Gln", "Gly", "His", "Ile", "Leu", "Lys", "Met",
+        "Phe", "Pro", "Ser", "Thr", "Trp", "Tyr", "Val",
+    ]);
+
     pub(crate) fn index(&self) -> usize {
         match self {
             AminoAcid::Alanine => 0,
@@ -68,36 +99,26 @@ impl AminoAcid {
 impl FromStr for AminoAcid {
     type Err = ();
 
+    /// Acepta el código de una o de tres letras, sin distinguir mayúsculas de minúsculas.
     fn from_str(s: &str) -> Result<Self, ()> {
-        match s {
-            "Ala" | "A" => Ok(AminoAcid::Alanine),
-            "Arg" | "R" => Ok(AminoAcid::Arginine),
-            "Asn" | "N" => Ok(AminoAcid::Asparagine),
-            "Asp" | "D" => Ok(AminoAcid::AsparticAcid),
-            "Cys" | "C" => Ok(AminoAcid::Cysteine),
-            "Glu" | "E" => Ok(AminoAcid::GlutamicAcid),
-            "Gln" | "Q" => Ok(AminoAcid::Glutamine),
-            "Gly" | "G" => Ok(AminoAcid::Glycine),
-            "His" | "H" => Ok(AminoAcid::Histidine),
-            "Ile" | "I" => Ok(AminoAcid::Isoleucine),
-            "Leu" | "L" => Ok(AminoAcid::Leucine),
-            "Lys" | "K" => Ok(AminoAcid::Lysine),
-            "Met" | "M" => Ok(AminoAcid::Methionine),
-            "Phe" | "F" => Ok(AminoAcid::Phenylalanine),
-            "Pro" | "P" => Ok(AminoAcid::Proline),
-            "Ser" | "S" => Ok(AminoAcid::Serine),
-            "Thr" | "T" => Ok(AminoAcid::Threonine),
-            "Trp" | "W" => Ok(AminoAcid::Tryptophan),
-            "Tyr" | "Y" => Ok(AminoAcid::Tyrosine),
-            "Val" | "V" => Ok(AminoAcid::Valine),
-            _ => Err(()), // Invalid input
-        }
+        let AminoAcidMap(single) = AminoAcid::SINGLE_LETTER_CODE;
+        let AminoAcidMap(three) = AminoAcid::THREE_LETTER_CODE;
+
+        let position = match s.len() {
+            1 => single
+                .iter()
+                .position(|c| s.eq_ignore_ascii_case(c.encode_utf8(&mut [0; 4]))),
+            3 => three.iter().position(|code| s.eq_ignore_ascii_case(code)),
+            _ => None,
+        };
+
+        position.map(|n| AminoAcid::ALL[n]).ok_or(()) // Invalid input
     }
 }
 
 /// Struct que permite relacional los 20 aminoacidos con cualquier tipo de datos.
 /// TODO: Mejorar la API, probablemente armando a través de un iterador.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct AminoAcidMap<T>(pub [T; 20]);
 
 impl<T> std::ops::Index<AminoAcid> for AminoAcidMap<T> {
@@ -116,4 +137,4 @@ impl<T> std::ops::IndexMut<AminoAcid> for AminoAcidMap<T> {
 
         variants.index_mut(index.index())
     }
-}
\ No newline at end of file
+}
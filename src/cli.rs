@@ -0,0 +1,720 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+#[cfg(feature = "uniprot")]
+use alphamissense_db::uniprot;
+use alphamissense_db::{
+    config::{self, Config},
+    features, genes,
+    hgnc::Hgnc,
+    liftover::Liftover,
+    proteins::Feature,
+    search, svg, vcf, AmClass, Assembly, BuildOptions, Cutoffs, DataBase, Encoding, FeatureScore,
+    GeneMeans, Genome, GenomicError, GenomicIndex, HeatmapOptions, Isoforms, ProteinStore, Summary,
+    Variation,
+};
+
+/// Buscador de patogenicidad en missense vía AlphaMissense.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(flatten)]
+    pub overrides: config::Overrides,
+    /// Escribe los resultados en JSON en lugar de texto.
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Si no se indica, se inicia el servidor.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Construye la base de datos a partir de la tabla de sustituciones.
+    Build {
+        /// Omite las filas inválidas en lugar de detenerse en la primera.
+        #[arg(long)]
+        lenient: bool,
+        /// Guarda las patogenicidades en punto fijo y deriva la clase de los puntos de corte.
+        #[arg(long)]
+        quantize: bool,
+        /// Punto de corte por debajo del cual una variante es posiblemente benigna.
+        #[arg(long, requires = "quantize", default_value_t = Cutoffs::default().benign)]
+        benign_cutoff: f32,
+        /// Punto de corte por encima del cual una variante es posiblemente patogénica.
+        #[arg(long, requires = "quantize", default_value_t = Cutoffs::default().pathogenic)]
+        pathogenic_cutoff: f32,
+    },
+    /// Consulta la patogenicidad de una variante.
+    Query {
+        /// Id de uniprot de la proteína, símbolo de su gen o id de Ensembl de uno de sus
+        /// transcritos.
+        gene: String,
+        /// Variación, como `R175H` o `p.Arg175His`.
+        variant: String,
+        /// Evalúa la variación en cada isoforma del gen.
+        #[arg(long)]
+        all_isoforms: bool,
+    },
+    /// Resume las predicciones de una proteína: su longitud, la media y la mediana de la
+    /// patogenicidad y la fracción de cada clase.
+    Summary {
+        /// Id de uniprot de la proteína, símbolo de su gen o id de Ensembl de uno de sus
+        /// transcritos.
+        gene: String,
+    },
+    /// Escribe la matriz de patogenicidades de una proteína, una fila por posición y una
+    /// columna por aminoácido.
+    Heatmap {
+        /// Id de uniprot de la proteína, o símbolo de su gen.
+        gene: String,
+        /// Primera posición.
+        #[arg(long)]
+        start: Option<u16>,
+        /// Última posición, incluida.
+        #[arg(long)]
+        end: Option<u16>,
+        /// Número máximo de filas; cada fila promedia varias posiciones si hacen falta.
+        #[arg(long)]
+        rows: Option<usize>,
+    },
+    /// Dibuja las predicciones de una proteína como SVG.
+    Plot {
+        /// Id de uniprot de la proteína, o símbolo de su gen.
+        gene: String,
+        /// Variaciones que se marcan en la gráfica, como `R175H`.
+        variants: Vec<String>,
+        /// Gráfica: `heatmap`, `profile` o `figure` (las dos).
+        #[arg(long, default_value_t = svg::Plot::Figure)]
+        plot: svg::Plot,
+        /// Archivo de salida. Si no se indica, se escribe a la salida estándar.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Primera posición.
+        #[arg(long)]
+        start: Option<u16>,
+        /// Última posición, incluida.
+        #[arg(long)]
+        end: Option<u16>,
+        /// Número máximo de posiciones a lo ancho; cada una promedia varias si hacen falta.
+        #[arg(long, default_value_t = svg::MAX_ROWS)]
+        rows: usize,
+    },
+    /// Busca genes por nombre.
+    Search {
+        text: String,
+        /// Muestra la media de la patogenicidad de cada gen encontrado.
+        #[arg(long)]
+        summary: bool,
+    },
+    /// Exporta la base de datos completa como una tabla de sustituciones, o como JSON
+    /// con un objeto por línea.
+    Export {
+        /// Archivo de salida. Si no se indica, se escribe a la salida estándar.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Construye el índice genómico a partir de la tabla genómica.
+    BuildGenome {
+        /// Omite las filas inválidas en lugar de detenerse en la primera.
+        #[arg(long)]
+        lenient: bool,
+        /// Ensamble de la tabla: `hg38` o `hg19` (también `GRCh38` o `GRCh37`).
+        #[arg(long, default_value_t = Assembly::Hg38)]
+        assembly: Assembly,
+    },
+    /// Construye la base de datos de isoformas a partir de la tabla de sustituciones de
+    /// isoformas.
+    BuildIsoforms {
+        /// Omite las filas inválidas en lugar de detenerse en la primera.
+        #[arg(long)]
+        lenient: bool,
+    },
+    /// Regenera el mapa de ids de uniprot a símbolos en el archivo `names` a partir de la
+    /// tabla completa de HGNC, y guarda los alias, símbolos previos, ids de Ensembl y
+    /// RefSeq y el tipo de locus de cada gen en el archivo `gene_info`.
+    BuildNames {
+        /// Tabla completa de HGNC, `hgnc_complete_set.txt`.
+        hgnc: PathBuf,
+    },
+    /// Guarda en el archivo `proteins` los nombres, la secuencia, los ids secundarios y las
+    /// regiones anotadas de cada proteína de la base de datos, tomados de un volcado local
+    /// de UniProtKB, y lista las proteínas que no están en él.
+    BuildProteins {
+        /// Volcado de UniProtKB, comprimido o no: el archivo de texto (`.dat`), XML o JSON.
+        dump: PathBuf,
+    },
+    /// Consulta a UniProt, en línea, los nombres de las proteínas indicadas o, si no se
+    /// indica ninguna, de las de la base de datos que no tienen nombre, y los guarda en el
+    /// archivo `gene_info`.
+    #[cfg(feature = "uniprot")]
+    FetchNames {
+        /// Ids de uniprot.
+        ids: Vec<String>,
+    },
+    /// Anota un VCF, comprimido con bgzip o no, con las predicciones del índice genómico.
+    Annotate {
+        /// VCF de entrada. Si no se indica, se lee de la entrada estándar.
+        vcf: Option<PathBuf>,
+        /// Archivo de salida. Si no se indica, se escribe a la salida estándar.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Ensamble de las coordenadas del VCF. Para hg19 se usa su índice o, si no
+        /// existe, el archivo de cadenas y el índice de hg38.
+        #[arg(long, default_value_t = Assembly::Hg38)]
+        assembly: Assembly,
+    },
+    /// Inicia el servidor.
+    Serve,
+}
+
+/// Una variante con su clasificación.
+#[derive(Serialize)]
+struct Scored<'a> {
+    uniprot_id: &'a str,
+    variant: String,
+    score: Option<f32>,
+    class: &'static str,
+    /// Regiones anotadas en UniProtKB que abarcan la posición.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    features: Vec<Feature>,
+}
+
+impl<'a> Scored<'a> {
+    fn new(uniprot_id: &'a str, variation: &Variation, class: AmClass) -> Self {
+        Scored {
+            uniprot_id,
+            variant: variation.to_string(),
+            score: class.score(),
+            class: class.name(),
+            features: Vec::new(),
+        }
+    }
+}
+
+/// Resumen de una proteína, con la media en cada una de sus regiones anotadas.
+#[derive(Serialize)]
+struct GeneSummary<'a> {
+    #[serde(flatten)]
+    summary: &'a Summary,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    features: &'a [FeatureScore],
+}
+
+/// Una región anotada en una línea, como `domain 100-200 (DNA binding)`.
+fn describe(feature: &Feature) -> String {
+    let positions = match feature.start == feature.end {
+        true => feature.start.to_string(),
+        false => format!("{}-{}", feature.start, feature.end),
+    };
+
+    match &feature.description {
+        Some(description) => format!("{} {} ({})", feature.kind, positions, description),
+        None => format!("{} {}", feature.kind, positions),
+    }
+}
+
+/// Ejecuta cualquier subcomando, excepto `serve`.
+pub fn run(command: Command, config: &Config, json: bool) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Build {
+            lenient,
+            quantize,
+            benign_cutoff,
+            pathogenic_cutoff,
+        } => {
+            let input = DataBase::input(config.input.as_deref())
+                .ok_or("No se encontraron las predicciones de AlphaMissense; indique --input")?;
+            let options = BuildOptions {
+                lenient,
+                encoding: match quantize {
+                    true => Encoding::Quantized(Cutoffs {
+                        benign: benign_cutoff,
+                        pathogenic: pathogenic_cutoff,
+                    }),
+                    false => Encoding::Full,
+                },
+            };
+
+            DataBase::build(input, &options)?.serialize(&config.database)?;
+            println!("Base de datos guardada en {}", config.database.display());
+        }
+        Command::BuildNames { hgnc } => {
+            let hgnc = Hgnc::load(&hgnc)?;
+            let map = hgnc.names();
+            std::fs::write(&config.names, serde_json::to_string(&map)?)?;
+            eprintln!(
+                "{} proteínas con nombre guardadas en {}",
+                map.len(),
+                config.names.display()
+            );
+
+            let count = genes::update_info(config, hgnc.gene_info())?;
+            eprintln!(
+                "Información de {} genes guardada en {}",
+                count,
+                config.gene_info.display()
+            );
+
+            if config.database.exists() {
+                let database = DataBase::load(&config.database)?;
+                let unmapped = hgnc.unmapped(&database);
+                if json {
+                    println!("{}", serde_json::to_string(&unmapped)?);
+                } else {
+                    eprintln!(
+                        "{} proteínas de la base de datos sin gen en HGNC:",
+                        unmapped.len()
+                    );
+                    for uniprot_id in unmapped {
+                        println!("{}", uniprot_id);
+                    }
+                }
+            }
+        }
+        Command::BuildProteins { dump } => {
+            let database = DataBase::load(&config.database)?;
+            let wanted = database.ids().map(str::to_owned).collect();
+            let summary = ProteinStore::build(&dump, &config.proteins, Some(wanted))?;
+            eprintln!(
+                "{} proteínas guardadas en {}",
+                summary.entries,
+                config.proteins.display()
+            );
+
+            // El índice de búsqueda se reconstruye con los nombres nuevos la próxima vez.
+            if config.index.exists() {
+                std::fs::remove_dir_all(&config.index)?;
+            }
+
+            if json {
+                println!("{}", serde_json::to_string(&summary.missing)?);
+            } else {
+                eprintln!(
+                    "{} proteínas de la base de datos sin entrada:",
+                    summary.missing.len()
+                );
+                for uniprot_id in summary.missing {
+                    println!("{}", uniprot_id);
+                }
+            }
+        }
+        #[cfg(feature = "uniprot")]
+        Command::FetchNames { ids } => {
+            let ids = match ids.is_empty() {
+                false => ids,
+                true => {
+                    let database = DataBase::load(&config.database)?;
+                    let known = match config.gene_info.exists() {
+                        true => genes::load_info(&config.gene_info)?,
+                        false => Vec::new(),
+                    };
+                    let named: std::collections::HashSet<&str> = known
+                        .iter()
+                        .filter(|gene| !gene.symbol.is_empty())
+                        .map(|gene| gene.uniprot_id.as_str())
+                        .collect();
+                    let mut ids: Vec<String> = database
+                        .ids()
+                        .filter(|id| genes::name(id).is_none() && !named.contains(id))
+                        .map(str::to_owned)
+                        .collect();
+                    ids.sort_unstable();
+                    ids
+                }
+            };
+
+            let client = uniprot::Client::new(uniprot::ClientOptions {
+                base_url: config.uniprot_url.clone(),
+                cache: Some(config.uniprot_cache.clone()),
+                ..uniprot::ClientOptions::default()
+            })?;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            let mut found = Vec::new();
+            runtime.block_on(async {
+                for id in &ids {
+                    match client.entry(id).await {
+                        Ok(entry) => found.push(entry.gene_info(id)),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+            });
+
+            if json {
+                println!("{}", serde_json::to_string(&found)?);
+            } else {
+                for gene in &found {
+                    println!("{}\t{}", gene.uniprot_id, gene.symbol);
+                }
+            }
+            eprintln!(
+                "{} de {} proteínas encontradas en UniProt",
+                found.len(),
+                ids.len()
+            );
+
+            let count = genes::update_info(config, found)?;
+            eprintln!(
+                "Información de {} genes guardada en {}",
+                count,
+                config.gene_info.display()
+            );
+        }
+        Command::BuildIsoforms { lenient } => {
+            let input = Isoforms::input(config.isoforms_input.as_deref()).ok_or(
+                "No se encontraron las predicciones de isoformas; indique --isoforms-input",
+            )?;
+            let options = BuildOptions {
+                lenient,
+                ..BuildOptions::default()
+            };
+
+            DataBase::build(input, &options)?.serialize(&config.isoforms)?;
+            println!(
+                "Base de datos de isoformas guardada en {}",
+                config.isoforms.display()
+            );
+        }
+        Command::Query {
+            gene,
+            variant,
+            all_isoforms,
+        } => {
+            let variation = Variation::from_str(&variant)?;
+            if all_isoforms || gene.to_uppercase().starts_with("ENST") {
+                let database = Isoforms::open(config)?.ok_or(
+                    "No existe la base de datos de isoformas; constrúyala con build-isoforms",
+                )?;
+                let results = match all_isoforms {
+                    true => database.query_gene(&gene, &variation),
+                    false => {
+                        let transcript = database.resolve(&gene).unwrap_or(&gene);
+                        vec![(transcript, database.query(transcript, &variation))]
+                    }
+                };
+                if results.is_empty() {
+                    return Err(format!("No se conocen isoformas de {}", gene).into());
+                }
+
+                for (transcript, class) in results {
+                    match class {
+                        Ok(class) if json => println!(
+                            "{}",
+                            serde_json::to_string(&Scored::new(transcript, &variation, class))?
+                        ),
+                        Ok(_) if variation.is_synonymous() => {
+                            println!("{} {}: Silenciosa", transcript, variation)
+                        }
+                        Ok(class) => println!("{} {}: {}", transcript, variation, class),
+                        Err(e) => eprintln!("{} {}: {}", transcript, variation, e),
+                    }
+                }
+                return Ok(());
+            }
+
+            let database = DataBase::load(&config.database)?;
+            let uniprot_id = database.resolve(&gene).unwrap_or(&gene);
+            let class = database.query(uniprot_id, &variation)?;
+            let features = match ProteinStore::open(config)? {
+                Some(proteins) => proteins
+                    .get(uniprot_id)?
+                    .map(|entry| features::covering(&entry, variation.position))
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            };
+
+            if json {
+                let scored = Scored {
+                    features,
+                    ..Scored::new(uniprot_id, &variation, class)
+                };
+                println!("{}", serde_json::to_string(&scored)?);
+            } else {
+                match variation.is_synonymous() {
+                    true => println!("{} {}: Silenciosa", uniprot_id, variation),
+                    false => println!("{} {}: {}", uniprot_id, variation, class),
+                }
+                for feature in features {
+                    println!("  {}", describe(&feature));
+                }
+            }
+        }
+        Command::Summary { gene } => {
+            let genome = Genome::new(GenomicIndex::load(&config.hg38).ok(), None, None);
+            let means = GeneMeans::open(config, &genome);
+            let mut features = Vec::new();
+            let (id, summary) = match gene.to_uppercase().starts_with("ENST") {
+                true => {
+                    let isoforms = Isoforms::open(config)?.ok_or(
+                        "No existe la base de datos de isoformas; constrúyala con build-isoforms",
+                    )?;
+                    let transcript = isoforms.resolve(&gene).unwrap_or(&gene).to_owned();
+                    let summary = isoforms.summary(&transcript, means.as_ref())?;
+                    (transcript, summary)
+                }
+                false => {
+                    let database = DataBase::load(&config.database)?;
+                    let uniprot_id = database.resolve(&gene).unwrap_or(&gene).to_owned();
+                    let summary = database.summary(&uniprot_id, means.as_ref())?;
+                    let entry = match ProteinStore::open(config)? {
+                        Some(proteins) => proteins.get(&uniprot_id)?,
+                        None => None,
+                    };
+                    if let Some(entry) = entry {
+                        features = database.feature_scores(&uniprot_id, &entry)?;
+                    }
+                    (uniprot_id, summary)
+                }
+            };
+
+            if json {
+                let summary = GeneSummary {
+                    summary: &summary,
+                    features: &features,
+                };
+                println!("{}", serde_json::to_string(&summary)?);
+            } else {
+                let number = |value: Option<f32>| match value {
+                    Some(value) => format!("{:.4}", value),
+                    None => "-".to_owned(),
+                };
+                println!("{}", id);
+                println!("Longitud\t{}", summary.length);
+                println!("Posiciones con predicciones\t{}", summary.covered);
+                println!("Sustituciones\t{}", summary.substitutions);
+                println!("Media\t{}", number(summary.mean));
+                println!("Mediana\t{}", number(summary.median));
+                println!("Media publicada\t{}", number(summary.published_mean));
+                println!("Posiblemente patogénicas\t{:.4}", summary.pathogenic);
+                println!("Ambiguas\t{:.4}", summary.ambiguous);
+                println!("Posiblemente benignas\t{:.4}", summary.benign);
+                for score in features {
+                    println!("{}\t{}", describe(&score.feature), number(score.mean));
+                }
+            }
+        }
+        Command::Heatmap {
+            gene,
+            start,
+            end,
+            rows,
+        } => {
+            let database = DataBase::load(&config.database)?;
+            let uniprot_id = database.resolve(&gene).unwrap_or(&gene);
+            let heatmap = database.heatmap(uniprot_id, &HeatmapOptions { start, end, rows })?;
+
+            if json {
+                println!("{}", serde_json::to_string(&heatmap)?);
+            } else {
+                let columns: Vec<String> = heatmap.columns.chars().map(String::from).collect();
+                println!("position\treference\t{}", columns.join("\t"));
+
+                let reference: Vec<char> = heatmap.reference.chars().collect();
+                let residues = reference.chunks(heatmap.step as usize);
+                for (row, (scores, residues)) in heatmap.scores.iter().zip(residues).enumerate() {
+                    let scores: Vec<String> = scores
+                        .iter()
+                        .map(|score| match score {
+                            Some(score) => format!("{:.4}", score),
+                            None => String::new(),
+                        })
+                        .collect();
+                    let residues: String = residues.iter().collect();
+                    println!(
+                        "{}\t{}\t{}",
+                        heatmap.position(row),
+                        residues,
+                        scores.join("\t")
+                    );
+                }
+            }
+        }
+        Command::Plot {
+            gene,
+            variants,
+            plot,
+            output,
+            start,
+            end,
+            rows,
+        } => {
+            let database = DataBase::load(&config.database)?;
+            let uniprot_id = database.resolve(&gene).unwrap_or(&gene);
+            let markers = variants
+                .iter()
+                .map(|variant| Variation::from_str(variant))
+                .collect::<Result<Vec<_>, _>>()?;
+            let options = HeatmapOptions {
+                start,
+                end,
+                rows: Some(rows),
+            };
+            let heatmap = database.heatmap(uniprot_id, &options)?;
+            let title = match genes::name(uniprot_id) {
+                Some(name) => format!("{} ({})", name, uniprot_id),
+                None => uniprot_id.to_owned(),
+            };
+            let svg = svg::render(&heatmap, plot, &title, &markers);
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, svg)?;
+                    eprintln!("Gráfica guardada en {}", path.display());
+                }
+                None => print!("{}", svg),
+            }
+        }
+        Command::Search { text, summary } => {
+            let index = search::index(&config.index, &genes::open(config)?)?;
+            let results = search::search(&index, &text)?;
+            let database = Isoforms::open(config)?;
+            let isoforms = |gene: &str| match &database {
+                Some(database) => database.isoforms(gene),
+                None => Vec::new(),
+            };
+            let summaries = match summary {
+                true => Some((
+                    DataBase::load(&config.database)?,
+                    GeneMeans::open(
+                        config,
+                        &Genome::new(GenomicIndex::load(&config.hg38).ok(), None, None),
+                    ),
+                )),
+                false => None,
+            };
+            let mean = |uniprot_id: &str| {
+                let (database, means) = summaries.as_ref()?;
+                let summary = database.summary(uniprot_id, means.as_ref()).ok()?;
+
+                summary.published_mean.or(summary.mean)
+            };
+
+            if json {
+                #[derive(Serialize)]
+                struct Gene<'a> {
+                    uniprot_id: &'a str,
+                    gene: &'a str,
+                    matched: search::Matched,
+                    exact: bool,
+                    isoforms: Vec<&'a str>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    mean: Option<f32>,
+                }
+
+                let results: Vec<_> = results
+                    .iter()
+                    .map(|hit| Gene {
+                        uniprot_id: &hit.uniprot_id,
+                        gene: &hit.gene,
+                        matched: hit.matched,
+                        exact: hit.exact,
+                        isoforms: isoforms(&hit.gene),
+                        mean: mean(&hit.uniprot_id),
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string(&results)?);
+            } else {
+                for hit in &results {
+                    let mut line = format!("{}\t{}\t{}", hit.uniprot_id, hit.gene, hit.matched);
+                    if summary {
+                        match mean(&hit.uniprot_id) {
+                            Some(mean) => line.push_str(&format!("\t{:.4}", mean)),
+                            None => line.push_str("\t-"),
+                        }
+                    }
+                    let transcripts = isoforms(&hit.gene).join(",");
+                    if !transcripts.is_empty() {
+                        line.push_str(&format!("\t{}", transcripts));
+                    }
+                    println!("{}", line);
+                }
+            }
+        }
+        Command::Export { output } => {
+            let database = DataBase::load(&config.database)?;
+            let mut out: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout().lock())),
+            };
+
+            if !json {
+                for line in &database.meta().header {
+                    writeln!(out, "{}", format!("# {}", line).trim_end())?;
+                }
+                writeln!(
+                    out,
+                    "uniprot_id\tprotein_variant\tam_pathogenicity\tam_class"
+                )?;
+            }
+
+            let mut ids: Vec<_> = database.ids().collect();
+            ids.sort_unstable();
+            for id in ids {
+                let gene = database
+                    .get(id)?
+                    .ok_or("Toda id listada tiene un gen asociado")?;
+                for (variation, class) in gene.variations() {
+                    if json {
+                        serde_json::to_writer(&mut out, &Scored::new(id, &variation, class))?;
+                        writeln!(out)?;
+                    } else {
+                        let score = class.score().unwrap_or(f32::NAN);
+                        writeln!(out, "{}\t{}\t{}\t{}", id, variation, score, class.name())?;
+                    }
+                }
+            }
+
+            out.flush()?;
+        }
+        Command::BuildGenome { lenient, assembly } => {
+            let (output, input) = match assembly {
+                Assembly::Hg38 => (&config.hg38, config.hg38_input.as_deref()),
+                Assembly::Hg19 => (&config.hg19, config.hg19_input.as_deref()),
+            };
+            let input = GenomicIndex::input(input, assembly)
+                .ok_or_else(|| format!("No se encontró la tabla genómica de {}", assembly))?;
+
+            GenomicIndex::build(input, output, assembly, lenient)?;
+            println!("Índice genómico guardado en {}", output.display());
+        }
+        Command::Annotate {
+            vcf,
+            output,
+            assembly,
+        } => {
+            // Solo se abren los índices que ya existen: construirlos tarda mucho.
+            let load = |path: &Path| path.exists().then(|| GenomicIndex::load(path)).transpose();
+            let liftover = config.chain.as_deref().map(Liftover::load).transpose()?;
+            let genome = Genome::new(load(&config.hg38)?, load(&config.hg19)?, liftover);
+            if !genome.assemblies().contains(&assembly) {
+                return Err(GenomicError::Unavailable { assembly }.into());
+            }
+
+            let vcf: Box<dyn Read + Send> = match vcf {
+                Some(path) => Box::new(File::open(path)?),
+                None => Box::new(io::stdin()),
+            };
+            let out: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout().lock())),
+            };
+
+            let summary = vcf::annotate(&genome, assembly, vcf, out)?;
+            eprintln!(
+                "Variantes anotadas: {} de {}",
+                summary.annotated, summary.variants
+            );
+        }
+        Command::Serve => unreachable!("El servidor se inicia desde main"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+#[cfg(feature = "cli")]
+use rocket::figment::{
+    providers::{Env, Serialized},
+    Figment,
+};
+use serde::{Deserialize, Serialize};
+
+/// Ubicación de los archivos que usa el programa.
+///
+/// Se toma, de menor a mayor prioridad, de los valores por omisión, de la configuración
+/// de Rocket (`Rocket.toml` y variables `ROCKET_*`), de las variables de entorno `AMDB_*`
+/// y de los argumentos de la línea de comandos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Archivo de la base de datos.
+    pub database: PathBuf,
+    /// Directorio del índice de búsqueda.
+    pub index: PathBuf,
+    /// Tabla de sustituciones de AlphaMissense, comprimida o no. Si no se indica, se busca
+    /// `AlphaMissense_aa_substitutions.tsv.gz` o `AlphaMissense_aa_substitutions.tsv` en
+    /// el directorio actual.
+    pub input: Option<PathBuf>,
+    /// Archivo del índice genómico.
+    pub hg38: PathBuf,
+    /// Tabla genómica de AlphaMissense, comprimida o no. Si no se indica, se busca
+    /// `AlphaMissense_hg38.tsv.gz` o `AlphaMissense_hg38.tsv` en el directorio actual.
+    pub hg38_input: Option<PathBuf>,
+    /// Archivo del índice genómico de hg19 (GRCh37).
+    pub hg19: PathBuf,
+    /// Tabla genómica de hg19 de AlphaMissense. Si no se indica, se busca
+    /// `AlphaMissense_hg19.tsv.gz` o `AlphaMissense_hg19.tsv` en el directorio actual.
+    pub hg19_input: Option<PathBuf>,
+    /// Archivo de cadenas de UCSC de hg19 a hg38, como `hg19ToHg38.over.chain.gz`. Si no
+    /// hay índice de hg19, se usa para convertir sus coordenadas a hg38.
+    pub chain: Option<PathBuf>,
+    /// Archivo de la base de datos de isoformas.
+    pub isoforms: PathBuf,
+    /// Tabla de sustituciones de todas las isoformas de AlphaMissense. Si no se indica, se
+    /// busca `AlphaMissense_isoforms_aa_substitutions.tsv.gz` o
+    /// `AlphaMissense_isoforms_aa_substitutions.tsv` en el directorio actual.
+    pub isoforms_input: Option<PathBuf>,
+    /// Tabla con el gen de cada transcrito, como la que exporta BioMart de Ensembl.
+    pub transcripts: Option<PathBuf>,
+    /// Mapa de ids de uniprot a símbolos que escribe `build-names`. Si no existe, se usa
+    /// el incluido en el programa.
+    pub names: PathBuf,
+    /// Archivo con los nombres de proteína, alias, símbolos previos e ids secundarios de
+    /// cada gen, para el buscador. Si no existe, solo se buscan símbolos e ids.
+    pub gene_info: PathBuf,
+    /// Tabla de medias de patogenicidad por transcrito de AlphaMissense. Si no se indica,
+    /// se busca `AlphaMissense_gene_hg38.tsv.gz` o `AlphaMissense_gene_hg38.tsv` en el
+    /// directorio actual.
+    pub gene_means: Option<PathBuf>,
+    /// Archivo con los nombres, la secuencia y las regiones anotadas de cada proteína,
+    /// tomados de un volcado de UniProtKB con `build-proteins`.
+    pub proteins: PathBuf,
+    /// Dirección de la API REST de UniProt, para `fetch-names`.
+    #[cfg(feature = "uniprot")]
+    pub uniprot_url: String,
+    /// Directorio donde se guardan las respuestas de UniProt.
+    #[cfg(feature = "uniprot")]
+    pub uniprot_cache: PathBuf,
+    /// Directorio con la compilación del frontend.
+    pub frontend: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database: "variations.cdv".into(),
+            index: "index".into(),
+            input: None,
+            hg38: "hg38.cdv".into(),
+            hg38_input: None,
+            hg19: "hg19.cdv".into(),
+            hg19_input: None,
+            chain: None,
+            isoforms: "isoforms.cdv".into(),
+            isoforms_input: None,
+            transcripts: None,
+            names: "names.json".into(),
+            gene_info: "genes.jsonl".into(),
+            gene_means: None,
+            proteins: "proteins.cdv".into(),
+            #[cfg(feature = "uniprot")]
+            uniprot_url: crate::uniprot::BASE_URL.into(),
+            #[cfg(feature = "uniprot")]
+            uniprot_cache: "uniprot".into(),
+            frontend: "frontend/build".into(),
+        }
+    }
+}
+
+/// Argumentos de la línea de comandos. Los que se indiquen tienen prioridad sobre el
+/// resto de la configuración.
+#[cfg(feature = "cli")]
+#[derive(Debug, Default, clap::Args, Serialize)]
+pub struct Overrides {
+    /// Archivo de la base de datos.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<PathBuf>,
+    /// Directorio del índice de búsqueda.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<PathBuf>,
+    /// Tabla de sustituciones de AlphaMissense.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<PathBuf>,
+    /// Archivo del índice genómico.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hg38: Option<PathBuf>,
+    /// Tabla genómica de AlphaMissense.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hg38_input: Option<PathBuf>,
+    /// Archivo del índice genómico de hg19.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hg19: Option<PathBuf>,
+    /// Tabla genómica de hg19 de AlphaMissense.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hg19_input: Option<PathBuf>,
+    /// Archivo de cadenas de UCSC de hg19 a hg38.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<PathBuf>,
+    /// Archivo de la base de datos de isoformas.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isoforms: Option<PathBuf>,
+    /// Tabla de sustituciones de todas las isoformas de AlphaMissense.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isoforms_input: Option<PathBuf>,
+    /// Tabla con el gen de cada transcrito.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcripts: Option<PathBuf>,
+    /// Mapa de ids de uniprot a símbolos.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub names: Option<PathBuf>,
+    /// Archivo con los alias y nombres de proteína de cada gen, para el buscador.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gene_info: Option<PathBuf>,
+    /// Tabla de medias de patogenicidad por transcrito de AlphaMissense.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gene_means: Option<PathBuf>,
+    /// Archivo con las entradas de UniProtKB de cada proteína.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proteins: Option<PathBuf>,
+    /// Dirección de la API REST de UniProt.
+    #[cfg(feature = "uniprot")]
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uniprot_url: Option<String>,
+    /// Directorio donde se guardan las respuestas de UniProt.
+    #[cfg(feature = "uniprot")]
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uniprot_cache: Option<PathBuf>,
+    /// Directorio con la compilación del frontend.
+    #[arg(long, global = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontend: Option<PathBuf>,
+}
+
+/// Reúne todas las fuentes de configuración. El resultado sirve tanto para extraer una
+/// [`Config`] como para configurar Rocket.
+#[cfg(feature = "cli")]
+pub fn figment(overrides: &Overrides) -> Figment {
+    rocket::Config::figment()
+        .join(Serialized::defaults(Config::default()))
+        .merge(Env::prefixed("AMDB_").global())
+        .merge(Serialized::globals(overrides))
+}
@@ -1,15 +1,25 @@
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::ops::IndexMut;
+use std::io::Read;
+use std::path::Path;
 use std::str::FromStr;
 use std::{collections::HashMap, io::BufRead};
 
 use bincode::Options;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     aminoacids::{AminoAcid, AminoAcidMap},
-    variations::{AmClass, Variation},
+    config::Config,
+    features::{self, FeatureScore},
+    format::{self, FormatError},
+    genes,
+    heatmap::{Heatmap, HeatmapOptions},
+    input,
+    proteins::Entry,
+    summary::{GeneMeans, Summary},
+    variations::{AmClass, Cutoffs, Variation},
 };
 
 /// Representa la información en una fila de la tabla de sustituciones.
@@ -19,22 +29,210 @@ pub struct Row {
     am_class: AmClass,
 }
 
-impl FromStr for Row {
-    type Err = ();
+/// Errores al construir la base de datos. Los errores de formato indican la línea del
+/// archivo (contando el encabezado) y la columna, ambas desde 1, junto al texto inválido.
+#[derive(Debug)]
+pub enum IngestError {
+    /// No se pudo leer el archivo de entrada.
+    Io(std::io::Error),
+    /// La fila termina antes de la columna indicada.
+    MissingColumn { line: usize, column: usize },
+    /// La variación no tiene la forma `R175H`.
+    BadVariation {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// La patogenicidad no es un número.
+    BadScore {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// La clasificación no es `benign`, `pathogenic` ni `ambiguous`.
+    UnknownClass {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// El encabezado no tiene una de las columnas necesarias.
+    MissingHeaderColumn { name: &'static str },
+    /// El cromosoma, la posición o los alelos de una variante genómica son inválidos.
+    BadCoordinate {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// Las filas de una región del genoma no están juntas: el archivo no está ordenado
+    /// por cromosoma y posición.
+    Unsorted { line: usize },
+    /// No se pudo escribir el resultado.
+    Output(FormatError),
+    /// La fila es de un ensamble del genoma distinto al que se está construyendo.
+    WrongAssembly {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+}
+
+impl IngestError {
+    /// Descripción breve del tipo de error, para el resumen del modo tolerante.
+    fn label(&self) -> &'static str {
+        match self {
+            IngestError::Io(_) => "errores de lectura",
+            IngestError::MissingColumn { .. } => "columnas faltantes",
+            IngestError::BadVariation { .. } => "variaciones inválidas",
+            IngestError::BadScore { .. } => "patogenicidades inválidas",
+            IngestError::UnknownClass { .. } => "clasificaciones desconocidas",
+            IngestError::MissingHeaderColumn { .. } => "columnas faltantes en el encabezado",
+            IngestError::BadCoordinate { .. } => "coordenadas inválidas",
+            IngestError::Unsorted { .. } => "filas desordenadas",
+            IngestError::Output(_) => "errores de escritura",
+            IngestError::WrongAssembly { .. } => "ensambles distintos",
+        }
+    }
+
+    /// El primero de dos errores en el archivo. Los lotes se procesan en desorden, así
+    /// que sin esto el error reportado dependería de qué hilo terminara antes.
+    fn first(self, other: IngestError) -> IngestError {
+        if other.line() < self.line() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn line(&self) -> usize {
+        match self {
+            IngestError::Io(_)
+            | IngestError::MissingHeaderColumn { .. }
+            | IngestError::Output(_) => 0,
+            IngestError::MissingColumn { line, .. }
+            | IngestError::BadVariation { line, .. }
+            | IngestError::BadScore { line, .. }
+            | IngestError::UnknownClass { line, .. }
+            | IngestError::BadCoordinate { line, .. }
+            | IngestError::Unsorted { line }
+            | IngestError::WrongAssembly { line, .. } => *line,
+        }
+    }
+}
+
+impl std::fmt::Display for IngestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IngestError::Io(e) => write!(f, "Error al leer las predicciones: {}", e),
+            IngestError::MissingColumn { line, column } => {
+                write!(f, "Línea {}: falta la columna {}", line, column)
+            }
+            IngestError::BadVariation { line, column, text } => {
+                write!(
+                    f,
+                    "Línea {}, columna {}: variación inválida {:?}",
+                    line, column, text
+                )
+            }
+            IngestError::BadScore { line, column, text } => {
+                write!(
+                    f,
+                    "Línea {}, columna {}: patogenicidad inválida {:?}",
+                    line, column, text
+                )
+            }
+            IngestError::UnknownClass { line, column, text } => {
+                write!(
+                    f,
+                    "Línea {}, columna {}: clasificación desconocida {:?}",
+                    line, column, text
+                )
+            }
+            IngestError::MissingHeaderColumn { name } => {
+                write!(f, "El encabezado no tiene la columna {}", name)
+            }
+            IngestError::BadCoordinate { line, column, text } => {
+                write!(
+                    f,
+                    "Línea {}, columna {}: coordenada inválida {:?}",
+                    line, column, text
+                )
+            }
+            IngestError::Unsorted { line } => write!(
+                f,
+                "Línea {}: el archivo no está ordenado por cromosoma y posición",
+                line
+            ),
+            IngestError::Output(e) => write!(f, "Error al escribir el resultado: {}", e),
+            IngestError::WrongAssembly {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Línea {}: la variante es de {}, pero se está construyendo el índice de {}",
+                line, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+impl From<std::io::Error> for IngestError {
+    fn from(e: std::io::Error) -> Self {
+        IngestError::Io(e)
+    }
+}
 
-    fn from_str(row: &str) -> Result<Self, Self::Err> {
-        let mut values = row.split("\t");
+/// Interpreta la patogenicidad y la clasificación de una fila, que están en las columnas
+/// `columns` (desde 1).
+pub(crate) fn parse_class(
+    score: &str,
+    class: &str,
+    line: usize,
+    columns: (usize, usize),
+) -> Result<AmClass, IngestError> {
+    let pathogenicity = score.parse().map_err(|_| IngestError::BadScore {
+        line,
+        column: columns.0,
+        text: score.to_owned(),
+    })?;
 
-        let uniprot_id = values.next().ok_or(())?.to_owned();
-        let protein_variant = Variation::from_str(values.next().ok_or(())?)?;
-        let pathogenicity = values.next().ok_or(())?.parse().map_err(|_| ())?;
-        let am_class = match values.next().ok_or(())? {
-            "benign" => AmClass::Benign(pathogenicity),
-            "pathogenic" => AmClass::Pathogenic(pathogenicity),
-            "ambiguous" => AmClass::Ambiguous(pathogenicity),
-            _ => return Err(()),
+    match class {
+        "benign" => Ok(AmClass::Benign(pathogenicity)),
+        "pathogenic" => Ok(AmClass::Pathogenic(pathogenicity)),
+        "ambiguous" => Ok(AmClass::Ambiguous(pathogenicity)),
+        _ => Err(IngestError::UnknownClass {
+            line,
+            column: columns.1,
+            text: class.to_owned(),
+        }),
+    }
+}
+
+impl Row {
+    /// Interpreta la fila que se encuentra en la línea `line` del archivo.
+    pub fn parse(row: &str, line: usize) -> Result<Self, IngestError> {
+        let mut values = row.split('\t');
+        let mut column = |column: usize| {
+            values
+                .next()
+                .ok_or(IngestError::MissingColumn { line, column })
         };
 
+        let uniprot_id = column(1)?.to_owned();
+        let variant = column(2)?;
+        let score = column(3)?;
+        let class = column(4)?;
+
+        let protein_variant =
+            Variation::from_str(variant).map_err(|_| IngestError::BadVariation {
+                line,
+                column: 2,
+                text: variant.to_owned(),
+            })?;
+        let am_class = parse_class(score, class, line, (3, 4))?;
+
         Ok(Row {
             uniprot_id,
             protein_variant,
@@ -43,193 +241,1252 @@ impl FromStr for Row {
     }
 }
 
+/// Opciones para construir la base de datos.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// En lugar de abortar ante la primera fila inválida, la omite, la cuenta y al
+    /// final imprime un resumen.
+    pub lenient: bool,
+    /// Cómo se guardarán las clasificaciones en el archivo.
+    pub encoding: Encoding,
+}
+
+/// Filas omitidas en modo tolerante.
+#[derive(Default)]
+pub(crate) struct Skipped {
+    counts: HashMap<&'static str, usize>,
+    /// Los primeros errores encontrados, para mostrarlos en el resumen.
+    examples: Vec<IngestError>,
+}
+
+impl Skipped {
+    const EXAMPLES: usize = 10;
+
+    pub(crate) fn push(&mut self, error: IngestError) {
+        *self.counts.entry(error.label()).or_default() += 1;
+        self.push_example(error);
+    }
+
+    fn merge(&mut self, other: Skipped) {
+        for (label, count) in other.counts {
+            *self.counts.entry(label).or_default() += count;
+        }
+        for error in other.examples {
+            self.push_example(error);
+        }
+    }
+
+    fn push_example(&mut self, error: IngestError) {
+        self.examples.push(error);
+        self.examples.sort_by_key(IngestError::line);
+        self.examples.truncate(Skipped::EXAMPLES);
+    }
+
+    pub(crate) fn summary(&self) {
+        let total: usize = self.counts.values().sum();
+        eprintln!("Filas omitidas: {}", total);
+
+        let mut counts: Vec<_> = self.counts.iter().collect();
+        counts.sort();
+        for (label, count) in counts {
+            eprintln!("  {}: {}", label, count);
+        }
+        for error in &self.examples {
+            eprintln!("  {}", error);
+        }
+    }
+}
+
+/// Resultado de procesar un lote de filas.
+#[derive(Default)]
+struct Fragment {
+    genes: HashMap<String, GeneVariations>,
+    skipped: Skipped,
+}
+
+impl Fragment {
+    fn merge(mut self, other: Fragment) -> Fragment {
+        for (uniprot_id, variations) in other.genes {
+            match self.genes.get_mut(&uniprot_id) {
+                Some(gene) => gene.merge(variations),
+                None => {
+                    self.genes.insert(uniprot_id, variations);
+                }
+            }
+        }
+        self.skipped.merge(other.skipped);
+
+        self
+    }
+}
+
+/// Razones por las que no se puede evaluar una variación en un gen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    /// La proteína no está en la base de datos.
+    UnknownProtein { uniprot_id: String },
+    /// El gen no tiene datos en esa posición, o es más corto.
+    UnknownPosition { position: u16 },
+    /// El aminoácido de referencia de la variación no es el de la proteína.
+    ReferenceMismatch {
+        position: u16,
+        expected: AminoAcid,
+        found: AminoAcid,
+    },
+    /// El bloque de la proteína en el archivo está dañado.
+    Corrupt { uniprot_id: String, message: String },
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::UnknownProtein { uniprot_id } => {
+                write!(f, "La proteína {} no está en la base de datos", uniprot_id)
+            }
+            QueryError::UnknownPosition { position } => {
+                write!(f, "No hay datos en la posición {}", position)
+            }
+            QueryError::ReferenceMismatch {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "La referencia no coincide: se esperaba {} en la posición {}, no {}",
+                AminoAcid::SINGLE_LETTER_CODE[*expected],
+                position,
+                AminoAcid::SINGLE_LETTER_CODE[*found]
+            ),
+            QueryError::Corrupt {
+                uniprot_id,
+                message,
+            } => write!(f, "No se pudo leer la proteína {}: {}", uniprot_id, message),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
 /// función posición en el gen: usize -> (Ali -> AmClass)
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct GeneVariations(Vec<Option<(AminoAcid, AminoAcidMap<AmClass>)>>);
 
 impl GeneVariations {
-    pub fn pathogenicity(&self, index: u16, variation: AminoAcid) -> Option<AmClass> {
-        let index = index as usize;
-        self.0.get(index).and_then(|e| e.as_ref().map(|(_, map)| map[variation]))
+    /// Clasificación de la sustitución por `variation` en `position`, contando desde 1,
+    /// sin revisar el aminoácido de referencia. Normalmente conviene usar
+    /// [`GeneVariations::query`].
+    pub fn pathogenicity(&self, position: u16, variation: AminoAcid) -> Option<AmClass> {
+        let index = (position as usize).checked_sub(1)?;
+        self.0
+            .get(index)
+            .and_then(|e| e.as_ref().map(|(_, map)| map[variation]))
+    }
+
+    /// Longitud de la proteína: la última posición con predicciones.
+    pub fn len(&self) -> u16 {
+        self.0.len() as u16
+    }
+
+    /// Si la proteína no tiene ninguna predicción.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Aminoácido de la proteína en `position`, contando desde 1.
+    pub fn reference(&self, position: u16) -> Option<AminoAcid> {
+        let index = (position as usize).checked_sub(1)?;
+        self.0
+            .get(index)
+            .and_then(|e| e.as_ref().map(|(base, _)| *base))
+    }
+
+    /// Clasificación de `variation`, después de revisar que su aminoácido de referencia
+    /// sea el de la proteína. Las variaciones sinónimas no tienen clasificación, así que
+    /// se regresan como [`AmClass::Undefined`] si la referencia coincide.
+    pub fn query(&self, variation: &Variation) -> Result<AmClass, QueryError> {
+        let Variation {
+            base,
+            position,
+            variant,
+        } = *variation;
+
+        match self.reference(position) {
+            None => Err(QueryError::UnknownPosition { position }),
+            Some(expected) if expected != base => Err(QueryError::ReferenceMismatch {
+                position,
+                expected,
+                found: base,
+            }),
+            Some(_) if variation.is_synonymous() => Ok(AmClass::Undefined),
+            Some(_) => Ok(self
+                .pathogenicity(position, variant)
+                .unwrap_or(AmClass::Undefined)),
+        }
+    }
+
+    /// Recorre todas las variaciones con clasificación definida, en orden de posición.
+    pub fn variations(&self) -> impl Iterator<Item = (Variation, AmClass)> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.as_ref().map(|entry| (index, entry)))
+            .flat_map(|(index, (base, AminoAcidMap(classes)))| {
+                AminoAcid::ALL
+                    .into_iter()
+                    .zip(classes)
+                    .filter(|(_, class)| class.score().is_some())
+                    .map(move |(variant, class)| {
+                        let variation = Variation {
+                            base: *base,
+                            position: index as u16 + 1,
+                            variant,
+                        };
+
+                        (variation, *class)
+                    })
+            })
+    }
+
+    /// Registra la clasificación de una variación, extendiendo el gen si la posición
+    /// está más allá de las conocidas hasta ahora.
+    pub(crate) fn insert(&mut self, protein_variant: Variation, am_class: AmClass) {
+        let Variation {
+            base,
+            position,
+            variant,
+        } = protein_variant;
+
+        let index = position as usize - 1;
+        if index >= self.0.len() {
+            self.0.resize_with(index + 1, || None);
+        }
+
+        let slot = &mut self.0[index];
+        match slot {
+            Some((_, map)) => map[variant] = am_class,
+            None => {
+                let mut map = AminoAcidMap([(); 20].map(|_| AmClass::Undefined));
+                map[variant] = am_class;
+                *slot = Some((base, map));
+            }
+        }
+    }
+
+    /// Cuenta las clasificaciones que cambiarían al derivarlas de `cutoffs`.
+    fn reclassified(&self, cutoffs: &Cutoffs) -> usize {
+        self.0
+            .iter()
+            .flatten()
+            .flat_map(|(_, AminoAcidMap(classes))| classes)
+            .filter(|class| {
+                class.score().is_some_and(|score| {
+                    std::mem::discriminant(*class)
+                        != std::mem::discriminant(&cutoffs.classify(score))
+                })
+            })
+            .count()
+    }
+
+    /// Codifica el gen para guardarlo en un bloque de la base de datos.
+    fn encode(&self, encoding: Encoding) -> Result<Vec<u8>, FormatError> {
+        match encoding {
+            Encoding::Full => Ok(format::options().serialize(self)?),
+            Encoding::Quantized(_) => {
+                let mut block = Vec::with_capacity(self.0.len() * Encoding::QUANTIZED_POSITION);
+                for position in &self.0 {
+                    match position {
+                        None => block.push(Encoding::EMPTY),
+                        Some((base, AminoAcidMap(classes))) => {
+                            block.push(base.index() as u8);
+                            for class in classes {
+                                block.extend(Encoding::quantize(*class).to_le_bytes());
+                            }
+                        }
+                    }
+                }
+
+                Ok(block)
+            }
+        }
+    }
+
+    /// Inverso de [`GeneVariations::encode`].
+    fn decode(block: &[u8], encoding: Encoding) -> Result<Self, FormatError> {
+        let cutoffs = match encoding {
+            Encoding::Full => return Ok(format::options().deserialize(block)?),
+            Encoding::Quantized(cutoffs) => cutoffs,
+        };
+
+        let mut positions = Vec::new();
+        let mut rest = block;
+        while let Some((&base, tail)) = rest.split_first() {
+            if base == Encoding::EMPTY {
+                positions.push(None);
+                rest = tail;
+                continue;
+            }
+
+            let base = AminoAcid::ALL
+                .get(base as usize)
+                .copied()
+                .ok_or(FormatError::Corrupt)?;
+            let scores = tail.get(..2 * 20).ok_or(FormatError::Corrupt)?;
+            let classes = std::array::from_fn(|n| {
+                let score = u16::from_le_bytes([scores[2 * n], scores[2 * n + 1]]);
+                Encoding::dequantize(score, &cutoffs)
+            });
+
+            positions.push(Some((base, AminoAcidMap(classes))));
+            rest = &tail[2 * 20..];
+        }
+
+        Ok(GeneVariations(positions))
+    }
+
+    /// Une dos fragmentos del mismo gen construidos por separado.
+    fn merge(&mut self, GeneVariations(other): GeneVariations) {
+        if other.len() > self.0.len() {
+            self.0.resize_with(other.len(), || None);
+        }
+
+        for (slot, entry) in self.0.iter_mut().zip(other) {
+            match (slot, entry) {
+                (_, None) => {}
+                (slot @ None, entry) => *slot = entry,
+                (Some((_, map)), Some((_, other))) => {
+                    let AminoAcidMap(classes) = other;
+                    for (class, other) in map.0.iter_mut().zip(classes) {
+                        if !matches!(other, AmClass::Undefined) {
+                            *class = other;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromIterator<(Variation, AmClass)> for GeneVariations {
+    /// Gen con las clasificaciones de cada variación, como si se hubieran leído de la
+    /// tabla de sustituciones en ese orden.
+    fn from_iter<I: IntoIterator<Item = (Variation, AmClass)>>(variations: I) -> Self {
+        let mut gene = GeneVariations(Vec::new());
+        for (variation, class) in variations {
+            gene.insert(variation, class);
+        }
+        gene
+    }
+}
+
+/// Cómo se guardan las clasificaciones de cada gen en el archivo.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Encoding {
+    /// Cada clasificación se guarda tal cual: la patogenicidad como `f32` y su clase.
+    #[default]
+    Full,
+    /// La patogenicidad se guarda en punto fijo con 4 decimales en un `u16`, la misma
+    /// precisión con la que se publica, y la clase se deriva de los puntos de corte.
+    /// Reduce el archivo a menos de la mitad.
+    Quantized(Cutoffs),
+}
+
+impl Encoding {
+    /// Escala del punto fijo: 4 decimales.
+    const SCALE: f32 = 10_000.0;
+    /// Valor reservado para las clasificaciones indefinidas.
+    const UNDEFINED: u16 = u16::MAX;
+    /// Aminoácido de referencia reservado para las posiciones sin datos.
+    const EMPTY: u8 = u8::MAX;
+    /// Bytes que ocupa una posición: el aminoácido de referencia y 20 patogenicidades.
+    const QUANTIZED_POSITION: usize = 1 + 2 * 20;
+
+    fn quantize(class: AmClass) -> u16 {
+        match class.score() {
+            Some(score) => (score.clamp(0.0, 1.0) * Encoding::SCALE).round() as u16,
+            None => Encoding::UNDEFINED,
+        }
+    }
+
+    fn dequantize(score: u16, cutoffs: &Cutoffs) -> AmClass {
+        match score {
+            Encoding::UNDEFINED => AmClass::Undefined,
+            score => cutoffs.classify(score as f32 / Encoding::SCALE),
+        }
+    }
+}
+
+/// Datos de la publicación de AlphaMissense con la que se construyó la base de datos.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// Nombre del archivo de predicciones.
+    pub source: String,
+    /// Líneas de comentario al inicio del archivo, sin el `#`.
+    pub header: Vec<String>,
+    /// Aviso de derechos de autor, tomado del encabezado.
+    pub copyright: Option<String>,
+    /// Licencia bajo la que se publican las predicciones, tomada del encabezado.
+    pub license: Option<String>,
+    /// Versión de la publicación, si el encabezado la menciona.
+    pub version: Option<String>,
+    /// SHA-256 del archivo de predicciones, tal como está en disco.
+    pub sha256: String,
+    /// Fecha de construcción, en formato RFC 3339.
+    pub built_at: String,
+}
+
+impl Metadata {
+    /// Metadatos de una base de datos construida antes de que se guardaran.
+    fn unknown() -> Self {
+        Metadata {
+            source: "Desconocido".to_owned(),
+            header: Vec::new(),
+            copyright: None,
+            license: None,
+            version: None,
+            sha256: String::new(),
+            built_at: String::new(),
+        }
+    }
+
+    /// Interpreta las líneas de comentario del encabezado.
+    pub(crate) fn from_header(source: &str, header: Vec<String>, sha256: String) -> Self {
+        let find = |word: &str| {
+            header
+                .iter()
+                .find(|line| line.to_lowercase().contains(word))
+                .cloned()
+        };
+
+        Metadata {
+            source: source.to_owned(),
+            copyright: find("copyright"),
+            license: find("license"),
+            version: find("version"),
+            header,
+            sha256,
+            built_at: time::OffsetDateTime::now_utc()
+                .format(&time::format_description::well_known::Rfc3339)
+                .expect("Toda fecha UTC se puede representar en RFC 3339"),
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DataBase(HashMap<String, GeneVariations>);
+/// Base de datos de patogenicidad, indexada por id de uniprot.
+pub struct DataBase {
+    store: Store,
+}
+
+/// Metadatos que se guardan en la tabla de genes del archivo.
+#[derive(Serialize, Deserialize)]
+struct Layout {
+    meta: Metadata,
+    encoding: Encoding,
+}
 
-fn percentage_completed(procedure: &str, count: usize, total: usize) {
-    let c = 100 * count;
-    let p = total / 100;
+/// Dónde se encuentran los genes.
+enum Store {
+    /// Recién construidos, todos en memoria.
+    Memory {
+        layout: Layout,
+        genes: HashMap<String, GeneVariations>,
+    },
+    /// En el archivo de la base de datos proyectado en memoria. Cada gen se decodifica
+    /// hasta que se consulta, así que abrir la base de datos solo lee la tabla de genes.
+    Mapped(format::Mapped<Layout>),
+}
 
-    if count % p == 0 {
-        println!("{}: {}%", procedure, c / total);
+/// Número de filas en AlphaMissense_aa_substitutions.tsv, para reportar el avance.
+const ROWS: usize = 216_175_355;
+
+/// Tamaño aproximado, en bytes, de los lotes de filas que se reparten entre los hilos.
+const CHUNK: usize = 1 << 22;
+
+pub(crate) fn percentage_completed(procedure: &str, previous: usize, count: usize, total: usize) {
+    let before = 100 * previous / total;
+    let now = 100 * count / total;
+
+    if now > before {
+        eprintln!("{}: {}%", procedure, now.min(100));
     }
 }
 
 impl DataBase {
-    const PATH: &'static str = "variations.cdv";
+    /// Archivos de entrada que se buscan si no se indica uno, en orden de preferencia.
+    const INPUT: [&'static str; 2] = [
+        "AlphaMissense_aa_substitutions.tsv.gz",
+        "AlphaMissense_aa_substitutions.tsv",
+    ];
 
-    pub fn open() -> Self {
-        let path = std::path::Path::new(DataBase::PATH);
+    /// Abre la base de datos indicada en la configuración. Si no existe, la construye.
+    pub fn open(config: &Config) -> Result<Self, FormatError> {
+        if !config.database.exists() {
+            DataBase::new(config.input.as_deref()).serialize(&config.database)?;
+        }
 
-        if path.exists() {
-            DataBase::load()
-        } else {
-            let database = DataBase::new();
-            database.serialize();
+        DataBase::load(&config.database)
+    }
+
+    /// Abre la base de datos sin leerla completa. Si fue escrita con una versión anterior
+    /// del formato, la convierte y la vuelve a guardar con la versión actual.
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        let file = File::open(path)?;
 
-            database
+        match format::Mapped::new(&file, format::VERSION) {
+            Ok(mapped) => Ok(DataBase {
+                store: Store::Mapped(mapped),
+            }),
+            // Solo difiere en los metadatos: los bloques no estaban codificados.
+            Err(FormatError::IncompatibleVersion {
+                found: format::BLOCKS,
+            }) => Ok(DataBase {
+                store: Store::Mapped(format::Mapped::new(&file, format::BLOCKS)?.map_meta(
+                    |meta| Layout {
+                        meta,
+                        encoding: Encoding::Full,
+                    },
+                )),
+            }),
+            Err(FormatError::IncompatibleVersion { found }) if found < format::VERSION => {
+                eprintln!("Migrando la base de datos al formato {}", format::VERSION);
+                DataBase::migrate(path, found)?.serialize(path)?;
+
+                DataBase::load(path)
+            }
+            Err(e) => Err(e),
         }
     }
 
-    pub fn load() -> Self {
-        let mut file = File::open(DataBase::PATH).expect("Error al abrir la base de datos");
+    /// Lee completa una base de datos escrita con una versión anterior del formato.
+    fn migrate(path: &Path, version: u32) -> Result<Self, FormatError> {
         let mut buf = Vec::new();
-        file.read_to_end(&mut buf)
-            .expect("Error al leer la base de datos");
+        File::open(path)?.read_to_end(&mut buf)?;
+        let payload = format::read_blob(&buf)?;
 
-        let options = bincode::config::DefaultOptions::new().with_varint_encoding();
+        let (meta, genes) = match version {
+            format::LEGACY => (Metadata::unknown(), format::options().deserialize(payload)?),
+            _ => format::options().deserialize(payload)?,
+        };
 
-        options
-            .deserialize(&buf)
-            .expect("Error en la deserialización de la Base de Datos")
+        Ok(DataBase {
+            store: Store::Memory {
+                layout: Layout {
+                    meta,
+                    encoding: Encoding::Full,
+                },
+                genes,
+            },
+        })
     }
 
-    pub fn new() -> Self {
-        //Generamos y asignamos la memoria para la estructura final
-        let mut genes: HashMap<String, GeneVariations> = rows()
-            .enumerate()
-            .fold(
-                HashMap::new(),
-                |mut map,
-                 (
-                    n,
-                    Row {
+    /// Regresa `input` o, si no se indica, la primera de las tablas de sustituciones en
+    /// [`DataBase::INPUT`] que exista.
+    pub fn input(input: Option<&Path>) -> Option<&Path> {
+        input::find(input, &DataBase::INPUT)
+    }
+
+    /// Construye la base de datos a partir de `input` o, si no se indica, de la tabla de
+    /// sustituciones que se encuentre en el directorio actual.
+    pub fn new(input: Option<&Path>) -> Self {
+        let input =
+            DataBase::input(input).expect("El programa necesita las predicciones de AlphaMissense");
+
+        DataBase::build(input, &BuildOptions::default())
+            .unwrap_or_else(|e| panic!("No se pudo construir la base de datos. {}", e))
+    }
+
+    /// Construye la base de datos a partir de la tabla de sustituciones en `path`.
+    ///
+    /// El archivo se lee una sola vez: un hilo lo parte en lotes de filas completas y
+    /// cada lote se procesa en paralelo en un fragmento de la base de datos. Como un gen
+    /// puede quedar repartido entre varios lotes, los fragmentos se unen al final.
+    pub fn build(path: &Path, options: &BuildOptions) -> Result<Self, IngestError> {
+        let (mut file, digest) = input::open_with_digest(path)?;
+
+        // El encabezado son líneas de comentario seguidas de los nombres de las columnas.
+        let mut header = Vec::new();
+        let mut line = String::new();
+        while file.read_line(&mut line)? > 0 {
+            match line.strip_prefix('#') {
+                Some(comment) => header.push(comment.trim().to_owned()),
+                None => break,
+            }
+            line.clear();
+        }
+        let header_lines = header.len() + 1;
+
+        // Cada lote lleva el número de línea de su primera fila. Después de un error de
+        // lectura ya no se leen más lotes.
+        let mut count = 0;
+        let mut failed = false;
+        let chunks = std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let mut chunk = String::with_capacity(CHUNK + 256);
+            let mut lines = 0;
+            while chunk.len() < CHUNK {
+                match file.read_line(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(_) => lines += 1,
+                    Err(e) => {
+                        failed = true;
+                        return Some(Err(e));
+                    }
+                }
+            }
+
+            percentage_completed("Añadiendo Datos", count, count + lines, ROWS);
+            let first = header_lines + count + 1;
+            count += lines;
+
+            (!chunk.is_empty()).then_some(Ok((first, chunk)))
+        });
+
+        let Fragment { genes, skipped } = chunks
+            .par_bridge()
+            .map(|chunk| {
+                let (first, chunk) = chunk?;
+                let mut fragment = Fragment::default();
+                for (n, row) in chunk.lines().enumerate() {
+                    let Row {
                         uniprot_id,
                         protein_variant,
-                        am_class: _,
-                    },
-                )| {
-                    percentage_completed("Conteo del tamaño de los genes", n, 216175355);
-                    if map.contains_key(&uniprot_id) {
-                        let v = *map
-                            .get(&uniprot_id)
-                            .expect("El condicional de arriba asegura que si existe la llave");
-                        if protein_variant.position > v {
-                            map.insert(uniprot_id.to_owned(), protein_variant.position);
+                        am_class,
+                    } = match Row::parse(row, first + n) {
+                        Ok(row) => row,
+                        Err(e) if options.lenient => {
+                            fragment.skipped.push(e);
+                            continue;
                         }
-                    } else {
-                        map.insert(uniprot_id.to_owned(), protein_variant.position);
-                    }
+                        Err(e) => return Err(e),
+                    };
+
+                    fragment
+                        .genes
+                        .entry(uniprot_id)
+                        .or_insert_with(|| GeneVariations(Vec::new()))
+                        .insert(protein_variant, am_class);
+                }
 
-                    map
+                Ok(fragment)
+            })
+            .reduce(
+                || Ok(Fragment::default()),
+                |a, b| match (a, b) {
+                    (Ok(a), Ok(b)) => Ok(a.merge(b)),
+                    (Err(a), Err(b)) => Err(a.first(b)),
+                    (Err(e), Ok(_)) | (Ok(_), Err(e)) => Err(e),
                 },
-            )
+            )?;
+
+        if options.lenient {
+            skipped.summary();
+        }
+
+        if let Encoding::Quantized(cutoffs) = options.encoding {
+            let differences: usize = genes.values().map(|gene| gene.reclassified(&cutoffs)).sum();
+            if differences > 0 {
+                eprintln!(
+                    "{} clasificaciones publicadas difieren de las que dan los puntos de corte",
+                    differences
+                );
+            }
+        }
+
+        let source = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+
+        Ok(DataBase {
+            store: Store::Memory {
+                layout: Layout {
+                    meta: Metadata::from_header(&source, header, digest.hex()),
+                    encoding: options.encoding,
+                },
+                genes,
+            },
+        })
+    }
+
+    /// Guarda la base de datos gen por gen, con la versión actual del formato. Se escribe
+    /// primero a un archivo temporal para no dejar a medias uno que ya exista, y el
+    /// temporal se borra si algo falla.
+    pub fn serialize(&self, path: &Path) -> Result<(), FormatError> {
+        let temporary = path.with_extension("tmp");
+        let result = (|| {
+            let mut writer = format::Writer::create(&temporary)?;
+            for id in self.ids() {
+                let gene = self
+                    .get(id)?
+                    .expect("Toda id listada tiene un gen asociado");
+                writer.add(id.to_owned(), &gene.encode(self.encoding())?)?;
+            }
+
+            writer.finish(&Layout {
+                meta: self.meta().clone(),
+                encoding: self.encoding(),
+            })?;
+            Ok(std::fs::rename(&temporary, path)?)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+
+        result
+    }
+
+    pub fn _genes_as_json(&self) {
+        let keys: Vec<_> = self.ids().collect();
+        let serialized = serde_json::to_string(&keys).unwrap();
+
+        println!("{}", serialized)
+    }
+
+    /// Ids de uniprot de todos los genes en la base de datos.
+    pub fn ids(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match &self.store {
+            Store::Memory { genes, .. } => Box::new(genes.keys().map(String::as_str)),
+            Store::Mapped(mapped) => Box::new(mapped.keys()),
+        }
+    }
+
+    /// Variaciones de la proteína con id de uniprot `key`. En una base de datos abierta
+    /// con [`DataBase::load`] solo se decodifica ese gen, y si su bloque está dañado se
+    /// regresa el error.
+    pub fn get(&self, key: &str) -> Result<Option<Cow<'_, GeneVariations>>, FormatError> {
+        match &self.store {
+            Store::Memory { genes, .. } => Ok(genes.get(key).map(Cow::Borrowed)),
+            Store::Mapped(mapped) => Ok(mapped
+                .block(key)?
+                .map(|block| GeneVariations::decode(block, mapped.meta().encoding))
+                .transpose()?
+                .map(Cow::Owned)),
+        }
+    }
+
+    /// Como [`DataBase::get`], pero una proteína ausente o dañada es un [`QueryError`].
+    fn variations(&self, key: &str) -> Result<Cow<'_, GeneVariations>, QueryError> {
+        self.get(key)
+            .map_err(|e| QueryError::Corrupt {
+                uniprot_id: key.to_owned(),
+                message: e.to_string(),
+            })?
+            .ok_or_else(|| QueryError::UnknownProtein {
+                uniprot_id: key.to_owned(),
+            })
+    }
+
+    /// Indica si la proteína con id de uniprot `key` está en la base de datos, sin
+    /// decodificar sus variaciones.
+    pub fn contains(&self, key: &str) -> bool {
+        match &self.store {
+            Store::Memory { genes, .. } => genes.contains_key(key),
+            Store::Mapped(mapped) => mapped.contains(key),
+        }
+    }
+
+    /// Id de uniprot de `protein`, que puede ser el id mismo o el símbolo de su gen. Si
+    /// varias proteínas tienen el mismo símbolo, se regresa la primera que esté en la base
+    /// de datos.
+    pub fn resolve<'a>(&self, protein: &'a str) -> Option<&'a str> {
+        if self.contains(protein) {
+            return Some(protein);
+        }
+
+        genes::uniprot_ids(protein)
+            .iter()
+            .map(String::as_str)
+            .find(|id| self.contains(id))
+    }
+
+    /// Clasificación de `variation` en la proteína con id de uniprot `key`, revisando su
+    /// aminoácido de referencia como [`GeneVariations::query`].
+    pub fn query(&self, key: &str, variation: &Variation) -> Result<AmClass, QueryError> {
+        self.variations(key)?.query(variation)
+    }
+
+    /// Resumen de las predicciones de la proteína con id de uniprot `key`, con la media
+    /// publicada si se tiene la tabla de medias.
+    pub fn summary(&self, key: &str, means: Option<&GeneMeans>) -> Result<Summary, QueryError> {
+        let gene = self.variations(key)?;
+
+        Ok(Summary {
+            published_mean: means.and_then(|means| means.mean(key)),
+            ..Summary::new(&gene)
+        })
+    }
+
+    /// Matriz de patogenicidades de la proteína con id de uniprot `key`, como
+    /// [`Heatmap::new`].
+    pub fn heatmap(&self, key: &str, options: &HeatmapOptions) -> Result<Heatmap, QueryError> {
+        let gene = self.variations(key)?;
+
+        Heatmap::new(&gene, options)
+    }
+
+    /// Patogenicidad media en cada región anotada de `entry`, en la proteína con id de
+    /// uniprot `key`, como [`features::scores`].
+    pub fn feature_scores(
+        &self,
+        key: &str,
+        entry: &Entry,
+    ) -> Result<Vec<FeatureScore>, QueryError> {
+        let gene = self.variations(key)?;
+
+        Ok(features::scores(entry, &gene))
+    }
+
+    /// Igual que [`DataBase::query`] para varias variaciones, en el mismo orden. Cada gen
+    /// se decodifica una sola vez, aunque tenga varias variaciones.
+    pub fn query_batch<'a>(
+        &self,
+        variations: impl IntoIterator<Item = (&'a str, &'a Variation)>,
+    ) -> Vec<Result<AmClass, QueryError>> {
+        let mut genes: HashMap<&str, Result<Cow<'_, GeneVariations>, QueryError>> = HashMap::new();
+
+        variations
             .into_iter()
-            .enumerate()
-            .map(|(n, (k, v))| {
-                percentage_completed("Creación de la estructura", n, 20516);
+            .map(|(key, variation)| {
+                genes
+                    .entry(key)
+                    .or_insert_with(|| self.variations(key))
+                    .as_ref()
+                    .map_err(Clone::clone)?
+                    .query(variation)
+            })
+            .collect()
+    }
+
+    /// Datos de la publicación de AlphaMissense con la que se construyó.
+    pub fn meta(&self) -> &Metadata {
+        &self.layout().meta
+    }
+
+    /// Cómo se guardan, o se guardarán, las clasificaciones en el archivo.
+    pub fn encoding(&self) -> Encoding {
+        self.layout().encoding
+    }
+
+    fn layout(&self) -> &Layout {
+        match &self.store {
+            Store::Memory { layout, .. } => layout,
+            Store::Mapped(mapped) => mapped.meta(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{BufWriter, Write};
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    const LETTERS: [char; 20] = [
+        'A', 'R', 'N', 'D', 'C', 'E', 'Q', 'G', 'H', 'I', 'L', 'K', 'M', 'F', 'P', 'S', 'T', 'W',
+        'Y', 'V',
+    ];
+
+    /// Crea una tabla de sustituciones con el encabezado de AlphaMissense, con `id` como
+    /// nombre de la columna de la proteína, y la deja lista para escribir sus filas.
+    fn create_table(name: &str, id: &str) -> (PathBuf, BufWriter<File>) {
+        let path = std::env::temp_dir().join(format!("{}-{}.tsv", name, std::process::id()));
+        let mut file = BufWriter::new(File::create(&path).unwrap());
+
+        writeln!(file, "# Copyright 2023 DeepMind Technologies Limited").unwrap();
+        writeln!(file, "#").unwrap();
+        writeln!(file, "# Licensed under CC BY-NC-SA 4.0 license").unwrap();
+        writeln!(file, "{}\tprotein_variant\tam_pathogenicity\tam_class", id).unwrap();
+
+        (path, file)
+    }
+
+    /// Tabla de sustituciones con las filas `rows`, con sus columnas separadas por
+    /// espacios, como `P04637 R175H 0.9977 pathogenic`.
+    pub(crate) fn substitutions_table(name: &str, id: &str, rows: &[&str]) -> PathBuf {
+        let (path, mut file) = create_table(name, id);
+        for row in rows {
+            writeln!(file, "{}", row.replace(' ', "\t")).unwrap();
+        }
+
+        path
+    }
+
+    /// Escribe una tabla de sustituciones sintética con el formato de AlphaMissense.
+    fn synthetic_tsv(name: &str, genes: usize, length: usize) -> PathBuf {
+        let (path, mut file) = create_table(name, "uniprot_id");
+
+        let mut seed: u32 = 0x2545_f491;
+        for gene in 0..genes {
+            for position in 1..=length {
+                let base = LETTERS[(gene + position) % 20];
+                for variant in LETTERS.into_iter().filter(|&v| v != base) {
+                    seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    // Con 4 decimales, como se publica.
+                    let score = ((seed >> 8) % 10_001) as f32 / 10_000.0;
+                    let class = match score {
+                        s if s < 0.34 => "benign",
+                        s if s > 0.564 => "pathogenic",
+                        _ => "ambiguous",
+                    };
+                    writeln!(
+                        file,
+                        "Q{:05}\t{}{}{}\t{:.4}\t{}",
+                        gene, base, position, variant, score, class
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        path
+    }
+
+    /// La construcción anterior: recorre el archivo dos veces en un solo hilo.
+    fn sequential(path: &Path) -> DataBase {
+        sequential_prefix(path, usize::MAX)
+    }
+
+    /// Igual que [`sequential`], pero solo con las primeras `rows` filas.
+    fn sequential_prefix(path: &Path, rows: usize) -> DataBase {
+        let rows = || {
+            std::io::BufReader::new(File::open(path).unwrap())
+                .lines()
+                .skip(4)
+                .take(rows)
+                .map(|row| Row::parse(&row.unwrap(), 0).unwrap())
+        };
+
+        let mut genes: HashMap<String, GeneVariations> = rows()
+            .fold(HashMap::new(), |mut map: HashMap<String, u16>, row| {
+                let length = map.entry(row.uniprot_id).or_insert(0);
+                *length = (*length).max(row.protein_variant.position);
+                map
+            })
+            .into_iter()
+            .map(|(k, v)| {
                 let mut vec = Vec::new();
                 vec.resize_with(v.into(), || None);
                 (k, GeneVariations(vec))
             })
             .collect();
 
-        for (
-            n,
-            Row {
-                uniprot_id,
-                protein_variant,
-                am_class,
+        for row in rows() {
+            genes
+                .get_mut(&row.uniprot_id)
+                .unwrap()
+                .insert(row.protein_variant, row.am_class);
+        }
+
+        DataBase {
+            store: Store::Memory {
+                layout: Layout {
+                    meta: Metadata::unknown(),
+                    encoding: Encoding::Full,
+                },
+                genes,
             },
-        ) in rows().enumerate()
-        {
-            percentage_completed("Añadiendo Datos", n, 216175355);
-            let Variation {
-                base,
-                position,
-                variant,
-            } = protein_variant;
+        }
+    }
+
+    /// Compara solo los genes: los metadatos incluyen la fecha de construcción.
+    fn same_genes(a: &DataBase, b: &DataBase) -> bool {
+        a.ids().count() == b.ids().count()
+            && a.ids().all(|id| a.get(id).unwrap() == b.get(id).unwrap())
+    }
 
-            let index = position as usize - 1;
+    #[test]
+    fn parallel_build_matches_sequential() {
+        let path = synthetic_tsv("construccion", 40, 150);
+        let path = path.as_path();
 
-            let slot = genes
-                .get_mut(&uniprot_id)
-                .expect("Todas las IDs están contenidas en un HashMap")
-                .0
-                .index_mut(index);
+        let database = DataBase::build(path, &BuildOptions::default()).unwrap();
+        assert!(same_genes(&database, &sequential(path)));
 
-            match slot {
-                Some((_, map)) => map[variant] = am_class,
-                None => {
-                    *slot = {
-                        let mut map = AminoAcidMap([(); 20].map(|_| AmClass::Undefined));
-                        map[variant] = am_class;
-                        Some((base, map))
-                    }
-                }
+        let meta = database.meta();
+        assert_eq!(
+            meta.copyright.as_deref(),
+            Some("Copyright 2023 DeepMind Technologies Limited")
+        );
+        assert_eq!(
+            meta.license.as_deref(),
+            Some("Licensed under CC BY-NC-SA 4.0 license")
+        );
+        assert_eq!(meta.sha256.len(), 64);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn bad_rows_are_reported_or_skipped() {
+        let path = synthetic_tsv("filas-invalidas", 2, 10);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "Q00000\tR11\t0.5\tambiguous").unwrap();
+        writeln!(file, "Q00000\tR11H\t0.5\tdudoso").unwrap();
+        let path = path.as_path();
+
+        // 4 líneas de encabezado y 2 × 10 × 19 filas válidas.
+        match DataBase::build(path, &BuildOptions::default()) {
+            Err(IngestError::BadVariation { line, column, text }) => {
+                assert_eq!((line, column, text.as_str()), (385, 2, "R11"))
             }
+            Err(other) => panic!("{:?}", other),
+            Ok(_) => panic!("La fila inválida debe detener la construcción"),
         }
 
-        DataBase(genes)
+        let lenient = BuildOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        assert!(same_genes(
+            &DataBase::build(path, &lenient).unwrap(),
+            &sequential_prefix(path, 380)
+        ));
+
+        std::fs::remove_file(path).unwrap();
     }
 
-    pub fn serialize(&self) {
-        let options = bincode::config::DefaultOptions::new().with_varint_encoding();
+    #[test]
+    fn strict_build_reports_the_first_bad_row() {
+        // Más de un lote, con una fila inválida al principio y otra al final.
+        let table = synthetic_tsv("primer-error", 60, 150);
+        let path = std::env::temp_dir().join(format!("primer-error-{}-b.tsv", std::process::id()));
+        let rows = std::fs::read_to_string(&table).unwrap();
+        let mut lines = rows.lines();
+        let mut file = BufWriter::new(File::create(&path).unwrap());
+        for header in lines.by_ref().take(4) {
+            writeln!(file, "{}", header).unwrap();
+        }
+        writeln!(file, "Q00000\tR1H\t0.5\tdudoso").unwrap();
+        for row in lines {
+            writeln!(file, "{}", row).unwrap();
+        }
+        writeln!(file, "Q00000\tR1\t0.5\tambiguous").unwrap();
+        drop(file);
+        assert!(rows.len() > CHUNK);
 
-        let serialized = options.serialize(&self).expect("Serialización fallida");
-        let mut file = File::create("variations.cdv").expect("Error al crear el archivo");
+        match DataBase::build(&path, &BuildOptions::default()) {
+            Err(IngestError::UnknownClass { line, .. }) => assert_eq!(line, 5),
+            Err(other) => panic!("{:?}", other),
+            Ok(_) => panic!("La fila inválida debe detener la construcción"),
+        }
 
-        file.write_all(&serialized)
-            .expect("No se pudo guardar la información");
+        std::fs::remove_file(table).unwrap();
+        std::fs::remove_file(path).unwrap();
     }
 
-    pub fn _genes_as_json(&self) {
-        let keys: Vec<_> = self.0.keys().collect();
-        let serialized = serde_json::to_string(&keys).unwrap();
+    #[test]
+    fn truncated_inputs_stop_the_build() {
+        let table = synthetic_tsv("truncada", 60, 150);
+        let path = std::env::temp_dir().join(format!("truncada-{}.tsv.gz", std::process::id()));
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&std::fs::read(&table).unwrap()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        std::fs::write(&path, &compressed[..compressed.len() / 2]).unwrap();
 
-        println!("{}", serialized)
+        match DataBase::build(&path, &BuildOptions::default()) {
+            Err(IngestError::Io(_)) => {}
+            Err(other) => panic!("{:?}", other),
+            Ok(_) => panic!("Un archivo truncado debe detener la construcción"),
+        }
+
+        std::fs::remove_file(table).unwrap();
+        std::fs::remove_file(path).unwrap();
     }
 
-    pub fn _serialize_to_json(&self) {
-        // Serialize the data to JSON
-        let json_data = serde_json::to_string(&self).unwrap();
+    #[test]
+    fn quantized_scores_keep_published_precision() {
+        let cutoffs = Cutoffs::default();
+        for fixed in 0..=10_000u16 {
+            let published = format!("{:.4}", fixed as f64 / 10_000.0);
+            let score: f32 = published.parse().unwrap();
 
-        // Create or open the "prueba.json" file for writing
-        let mut file = File::create("prueba.json").unwrap();
+            let quantized = Encoding::quantize(AmClass::Ambiguous(score));
+            assert_eq!(quantized, fixed);
 
-        // Write the JSON data to the file
-        file.write_all(json_data.as_bytes()).unwrap();
+            let restored = Encoding::dequantize(quantized, &cutoffs);
+            assert_eq!(restored, cutoffs.classify(score));
+            assert_eq!(format!("{:.4}", restored.score().unwrap()), published);
+        }
+        assert_eq!(
+            Encoding::dequantize(Encoding::quantize(AmClass::Undefined), &cutoffs),
+            AmClass::Undefined
+        );
     }
 
-    pub fn _deserialize_from_json(&self) -> Self {
-        // Open the "prueba.json" file for reading
-        let mut file = File::open("prueba.json").unwrap();
-        let mut json_data = String::new();
+    #[test]
+    fn quantized_genes_round_trip() {
+        let path = synthetic_tsv("cuantizada", 20, 300);
+        let database = DataBase::build(&path, &BuildOptions::default()).unwrap();
+        let quantized = Encoding::Quantized(Cutoffs::default());
 
-        // Read the contents of the file into a String
-        file.read_to_string(&mut json_data).unwrap();
+        let (mut full_size, mut quantized_size) = (0, 0);
+        for id in database.ids() {
+            let gene = database.get(id).unwrap().unwrap();
+            let full = gene.encode(Encoding::Full).unwrap();
+            let compact = gene.encode(quantized).unwrap();
+            full_size += full.len();
+            quantized_size += compact.len();
+
+            assert_eq!(
+                GeneVariations::decode(&full, Encoding::Full).unwrap(),
+                *gene
+            );
+            assert_eq!(GeneVariations::decode(&compact, quantized).unwrap(), *gene);
+        }
 
-        // Deserialize the JSON data into a Prueba struct
-        let data: Self = serde_json::from_str(&json_data).unwrap();
+        assert!(2 * quantized_size < full_size);
 
-        data
+        std::fs::remove_file(path).unwrap();
     }
 
-    pub fn get(&self, key: &str) -> &GeneVariations {
-        &self.0[key]
+    #[test]
+    fn queries_check_the_reference_residue() {
+        let gene: GeneVariations = [
+            (
+                Variation::from_str("R175H").unwrap(),
+                AmClass::Pathogenic(0.9),
+            ),
+            (Variation::from_str("R175C").unwrap(), AmClass::Benign(0.1)),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            gene.query(&Variation::from_str("R175H").unwrap()),
+            Ok(AmClass::Pathogenic(0.9))
+        );
+        assert_eq!(gene.reference(175), Some(AminoAcid::Arginine));
+        assert_eq!(gene.reference(174), None);
+        assert_eq!(
+            gene.query(&Variation::from_str("A175H").unwrap()),
+            Err(QueryError::ReferenceMismatch {
+                position: 175,
+                expected: AminoAcid::Arginine,
+                found: AminoAcid::Alanine
+            })
+        );
+        assert_eq!(
+            gene.query(&Variation::from_str("p.Arg175=").unwrap()),
+            Ok(AmClass::Undefined)
+        );
+        assert!(gene
+            .query(&Variation::from_str("p.Ala175=").unwrap())
+            .is_err());
+        assert_eq!(
+            gene.query(&Variation::from_str("R176H").unwrap()),
+            Err(QueryError::UnknownPosition { position: 176 })
+        );
     }
-}
 
-/// Regresa un iterador sobre las filas de Alphamissense
-fn rows() -> Box<dyn Iterator<Item = Row>> {
-    let path = "AlphaMissense_aa_substitutions.tsv";
-    let file =
-        File::open(path).expect("El programa necesita las predicciones de AlphaMissense");
-    Box::new(std::io::BufReader::new(file)
-    .lines()
-    .skip(4)
-    .map(|row| Row::from_str(
-        &row.expect("El buffer debe de poder leer.")
-    ).expect("El formato del documento debe de poder parsearse.")))
-}
\ No newline at end of file
+    #[test]
+    fn batch_queries_match_single_queries() {
+        let path = synthetic_tsv("lotes", 3, 50);
+        let database = DataBase::build(&path, &BuildOptions::default()).unwrap();
+
+        let variations: Vec<(&str, Variation)> = [
+            ("Q00001", "A19C"),
+            ("Q00002", "A18C"),
+            ("Q00001", "A19="),
+            ("Q00001", "R19C"),
+            ("Q00001", "A99C"),
+            ("Q99999", "A19C"),
+        ]
+        .into_iter()
+        .map(|(id, variant)| (id, Variation::from_str(variant).unwrap()))
+        .collect();
+
+        let batch = database.query_batch(variations.iter().map(|(id, v)| (*id, v)));
+        let single: Vec<_> = variations
+            .iter()
+            .map(|(id, v)| database.query(id, v))
+            .collect();
+        assert_eq!(batch, single);
+
+        assert!(batch[0].as_ref().is_ok_and(|class| class.score().is_some()));
+        assert_eq!(batch[2], Ok(AmClass::Undefined));
+        assert!(matches!(
+            batch[3],
+            Err(QueryError::ReferenceMismatch { .. })
+        ));
+        assert_eq!(batch[4], Err(QueryError::UnknownPosition { position: 99 }));
+        assert!(matches!(batch[5], Err(QueryError::UnknownProtein { .. })));
+        assert_eq!(database.resolve("Q00001"), Some("Q00001"));
+        assert_eq!(database.resolve("Q99999"), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failed_serializations_leave_no_temporary_file() {
+        let table = synthetic_tsv("sin-temporal", 1, 10);
+        let database = DataBase::build(&table, &BuildOptions::default()).unwrap();
+
+        // No se puede reemplazar un directorio con el archivo.
+        let path = table.with_extension("d");
+        std::fs::create_dir_all(&path).unwrap();
+        assert!(matches!(database.serialize(&path), Err(FormatError::Io(_))));
+        assert!(!path.with_extension("tmp").exists());
+
+        std::fs::remove_dir(path).unwrap();
+        std::fs::remove_file(table).unwrap();
+    }
+
+    #[test]
+    fn corrupt_genes_are_query_errors() {
+        let table = synthetic_tsv("gen-danado", 1, 10);
+        let path = table.with_extension("cdv");
+        DataBase::build(&table, &BuildOptions::default())
+            .unwrap()
+            .serialize(&path)
+            .unwrap();
+
+        // El único bloque empieza justo después del encabezado.
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[format::HEADER] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let database = DataBase::load(&path).unwrap();
+        assert!(matches!(database.get("Q00000"), Err(FormatError::Corrupt)));
+        assert!(matches!(
+            database.query("Q00000", &Variation::from_str("A1C").unwrap()),
+            Err(QueryError::Corrupt { .. })
+        ));
+
+        std::fs::remove_file(table).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// `cargo test --release -- --ignored --nocapture benchmark`
+    #[test]
+    #[ignore]
+    fn benchmark_build() {
+        let path = synthetic_tsv("benchmark", 2_000, 400);
+        let path = path.as_path();
+
+        let start = Instant::now();
+        let old = sequential(path);
+        let sequential_time = start.elapsed();
+
+        let start = Instant::now();
+        let new = DataBase::build(path, &BuildOptions::default()).unwrap();
+        let parallel_time = start.elapsed();
+
+        println!("Dos pasadas, un hilo: {:?}", sequential_time);
+        println!(
+            "Una pasada, {} hilos: {:?}",
+            rayon::current_num_threads(),
+            parallel_time
+        );
+        assert!(same_genes(&old, &new));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Serialize;
+
+use crate::{
+    aminoacids::AminoAcid,
+    database::GeneVariations,
+    proteins::{Entry, Feature, ACTIVE_SITE, BINDING_SITE, DISULFIDE_BOND, DOMAIN, TRANSMEMBRANE},
+};
+
+/// Tipos de región que se comparan con las predicciones, con los nombres de
+/// [`Feature::kind`].
+pub const KINDS: [&str; 5] = [
+    DOMAIN,
+    ACTIVE_SITE,
+    BINDING_SITE,
+    DISULFIDE_BOND,
+    TRANSMEMBRANE,
+];
+
+/// Patogenicidad media de las sustituciones en una región anotada.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FeatureScore {
+    #[serde(flatten)]
+    pub feature: Feature,
+    /// Sustituciones con patogenicidad en la región.
+    pub substitutions: usize,
+    pub mean: Option<f32>,
+}
+
+impl FeatureScore {
+    pub fn new(feature: &Feature, gene: &GeneVariations) -> Self {
+        let scores: Vec<f32> = feature
+            .positions()
+            .into_iter()
+            .flat_map(|position| {
+                AminoAcid::ALL
+                    .into_iter()
+                    .filter_map(move |variant| gene.pathogenicity(position, variant)?.score())
+            })
+            .collect();
+
+        FeatureScore {
+            feature: feature.clone(),
+            substitutions: scores.len(),
+            mean: (!scores.is_empty()).then(|| {
+                (scores.iter().map(|&s| s as f64).sum::<f64>() / scores.len() as f64) as f32
+            }),
+        }
+    }
+}
+
+/// Regiones de `entry` de los tipos de [`KINDS`], en orden de posición, con la media de
+/// las predicciones de `gene` en cada una.
+pub fn scores(entry: &Entry, gene: &GeneVariations) -> Vec<FeatureScore> {
+    let mut features = overlaid(entry);
+    features.sort_by_key(|feature| (feature.start, feature.end));
+
+    features
+        .into_iter()
+        .map(|feature| FeatureScore::new(feature, gene))
+        .collect()
+}
+
+/// Regiones de `entry` de los tipos de [`KINDS`] que abarcan `position`.
+pub fn covering(entry: &Entry, position: u16) -> Vec<Feature> {
+    overlaid(entry)
+        .into_iter()
+        .filter(|feature| feature.covers(position))
+        .cloned()
+        .collect()
+}
+
+fn overlaid(entry: &Entry) -> Vec<&Feature> {
+    entry
+        .features
+        .iter()
+        .filter(|feature| KINDS.contains(&feature.kind.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{AmClass, Variation};
+
+    #[test]
+    fn averages_each_feature() {
+        let feature = |kind: &str, start, end| Feature {
+            kind: kind.into(),
+            start,
+            end,
+            description: None,
+        };
+        let entry = Entry {
+            accession: "P1".into(),
+            features: vec![
+                feature("transmembrane region", 2, 3),
+                feature("disulfide bond", 1, 3),
+                feature("modified residue", 2, 2),
+            ],
+            ..Entry::default()
+        };
+
+        let gene: GeneVariations = [
+            ("M1A", AmClass::Benign(0.1)),
+            ("K2A", AmClass::Benign(0.3)),
+            ("K2E", AmClass::Ambiguous(0.5)),
+            ("C3A", AmClass::Pathogenic(0.9)),
+        ]
+        .into_iter()
+        .map(|(variant, class)| (Variation::from_str(variant).unwrap(), class))
+        .collect();
+
+        let scores = scores(&entry, &gene);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].feature.kind, "disulfide bond");
+        assert_eq!(scores[0].substitutions, 2);
+        assert!((scores[0].mean.unwrap() - 0.5).abs() < 1e-6);
+        assert_eq!(scores[1].substitutions, 3);
+        assert!((scores[1].mean.unwrap() - 1.7 / 3.0).abs() < 1e-6);
+
+        let kinds = |position| -> Vec<String> {
+            covering(&entry, position)
+                .into_iter()
+                .map(|feature| feature.kind)
+                .collect()
+        };
+        assert_eq!(kinds(1), ["disulfide bond"]);
+        assert_eq!(kinds(2), ["transmembrane region"]);
+        assert_eq!(kinds(3), ["transmembrane region", "disulfide bond"]);
+    }
+}
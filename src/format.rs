@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use bincode::Options;
+use memmap2::Mmap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Bytes con los que inicia todo archivo de la base de datos. Como en PNG, incluyen un
+/// byte no ASCII y saltos de línea para detectar archivos dañados al tratarlos como texto.
+pub const MAGIC: [u8; 8] = *b"\x89AMDB\r\n\x1a";
+
+/// Versión del formato que escribe este programa. Tiene la misma estructura que
+/// [`BLOCKS`]; solo cambian los metadatos, que ahora indican cómo se codificó cada bloque.
+pub const VERSION: u32 = 3;
+
+/// Versión con bloques independientes, uno por llave, seguidos de una tabla con la
+/// posición de cada bloque.
+pub const BLOCKS: u32 = 2;
+
+/// Versión con un solo bincode de toda la base de datos tras el encabezado.
+pub const SINGLE_BLOB: u32 = 1;
+
+/// Versión asignada a los archivos sin encabezado: un bincode crudo de los genes, como
+/// se escribía antes de que existiera este formato.
+pub const LEGACY: u32 = 0;
+
+/// Tamaño del encabezado del formato 1: número mágico, versión, longitud y CRC32.
+const BLOB_HEADER: usize = MAGIC.len() + 4 + 8 + 4;
+
+/// Tamaño del encabezado de los formatos con bloques: número mágico, versión, y
+/// posición, longitud y CRC32 de la tabla de bloques.
+pub(crate) const HEADER: usize = MAGIC.len() + 4 + 8 + 8 + 4;
+
+/// Configuración de bincode con la que se codifica todo el contenido.
+pub fn options() -> impl Options {
+    bincode::config::DefaultOptions::new().with_varint_encoding()
+}
+
+/// Errores al leer un archivo de la base de datos.
+#[derive(Debug)]
+pub enum FormatError {
+    /// No se pudo leer o escribir el archivo.
+    Io(io::Error),
+    /// El archivo fue escrito por una versión del programa que usa otro formato.
+    IncompatibleVersion { found: u32 },
+    /// El archivo está truncado o su contenido no coincide con el CRC32 del encabezado.
+    Corrupt,
+    /// El contenido no corresponde con las estructuras de esta versión.
+    Decode(bincode::Error),
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Io(e) => write!(f, "Error al acceder a la base de datos: {}", e),
+            FormatError::IncompatibleVersion { found } => write!(
+                f,
+                "La base de datos fue construida con una versión incompatible del programa \
+                 (formato {}, se admiten del {} al {}). Es necesario reconstruirla.",
+                found, LEGACY, VERSION
+            ),
+            FormatError::Corrupt => write!(
+                f,
+                "La base de datos está dañada: su contenido no coincide con la suma de verificación."
+            ),
+            FormatError::Decode(e) => write!(f, "Error al decodificar la base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> Self {
+        FormatError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for FormatError {
+    fn from(e: bincode::Error) -> Self {
+        FormatError::Decode(e)
+    }
+}
+
+/// Regresa la versión del formato de un archivo. Los archivos sin número mágico se
+/// consideran de la versión [`LEGACY`].
+pub fn version(bytes: &[u8]) -> Result<u32, FormatError> {
+    if !bytes.starts_with(&MAGIC) {
+        return Ok(LEGACY);
+    }
+
+    let version = u32_at(bytes, MAGIC.len())?;
+    if version > VERSION {
+        return Err(FormatError::IncompatibleVersion { found: version });
+    }
+
+    Ok(version)
+}
+
+/// Regresa el contenido de un archivo de las versiones [`LEGACY`] o [`SINGLE_BLOB`],
+/// validando su CRC32.
+pub fn read_blob(bytes: &[u8]) -> Result<&[u8], FormatError> {
+    match version(bytes)? {
+        LEGACY => Ok(bytes),
+        SINGLE_BLOB => {
+            let length = u64_at(bytes, MAGIC.len() + 4)?;
+            let checksum = u32_at(bytes, MAGIC.len() + 12)?;
+            let payload = &bytes[BLOB_HEADER..];
+
+            if payload.len() as u64 != length || crc32fast::hash(payload) != checksum {
+                return Err(FormatError::Corrupt);
+            }
+
+            Ok(payload)
+        }
+        found => Err(FormatError::IncompatibleVersion { found }),
+    }
+}
+
+fn u32_at(bytes: &[u8], start: usize) -> Result<u32, FormatError> {
+    bytes
+        .get(start..start + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().expect("El campo mide 4 bytes")))
+        .ok_or(FormatError::Corrupt)
+}
+
+fn u64_at(bytes: &[u8], start: usize) -> Result<u64, FormatError> {
+    bytes
+        .get(start..start + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().expect("El campo mide 8 bytes")))
+        .ok_or(FormatError::Corrupt)
+}
+
+/// Posición de un bloque dentro del archivo.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Block {
+    offset: u64,
+    length: u64,
+    crc: u32,
+}
+
+/// Tabla al final del archivo: los metadatos y la posición de cada bloque.
+#[derive(Serialize, Deserialize)]
+struct Table<M> {
+    meta: M,
+    blocks: HashMap<String, Block>,
+}
+
+/// Escribe un archivo de la versión actual, bloque por bloque, sin necesidad de tener
+/// todo el contenido en memoria.
+pub struct Writer {
+    file: BufWriter<File>,
+    offset: u64,
+    blocks: HashMap<String, Block>,
+}
+
+impl Writer {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        // El encabezado se completa al terminar, cuando ya se conoce la tabla.
+        file.write_all(&[0; HEADER])?;
+
+        Ok(Writer {
+            file,
+            offset: HEADER as u64,
+            blocks: HashMap::new(),
+        })
+    }
+
+    /// Agrega un bloque que se podrá leer después con [`Mapped::block`].
+    pub fn add(&mut self, key: String, block: &[u8]) -> io::Result<()> {
+        self.file.write_all(block)?;
+        self.blocks.insert(
+            key,
+            Block {
+                offset: self.offset,
+                length: block.len() as u64,
+                crc: crc32fast::hash(block),
+            },
+        );
+        self.offset += block.len() as u64;
+
+        Ok(())
+    }
+
+    /// Escribe la tabla de bloques junto con los metadatos y completa el encabezado.
+    pub fn finish<M: Serialize>(mut self, meta: &M) -> Result<(), FormatError> {
+        let table = options().serialize(&Table {
+            meta,
+            blocks: self.blocks,
+        })?;
+        self.file.write_all(&table)?;
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&MAGIC)?;
+        self.file.write_all(&VERSION.to_le_bytes())?;
+        self.file.write_all(&self.offset.to_le_bytes())?;
+        self.file.write_all(&(table.len() as u64).to_le_bytes())?;
+        self.file
+            .write_all(&crc32fast::hash(&table).to_le_bytes())?;
+        self.file.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Archivo con bloques proyectado en memoria. Solo la tabla de bloques se lee al
+/// abrirlo; cada bloque se lee del disco, y se valida, hasta que se pide.
+pub struct Mapped<M> {
+    mmap: Mmap,
+    meta: M,
+    blocks: HashMap<String, Block>,
+}
+
+impl<M: DeserializeOwned> Mapped<M> {
+    /// Proyecta en memoria un archivo ya abierto, que debe ser de la versión `expected`.
+    /// Los metadatos de cada versión pueden ser de un tipo distinto.
+    ///
+    /// Como con cualquier proyección en memoria, el archivo no debe modificarse mientras
+    /// esté abierto.
+    pub fn new(file: &File, expected: u32) -> Result<Self, FormatError> {
+        let mmap = unsafe { Mmap::map(file)? };
+
+        match version(&mmap)? {
+            found if found == expected => {}
+            found => return Err(FormatError::IncompatibleVersion { found }),
+        }
+
+        let offset = u64_at(&mmap, MAGIC.len() + 4)? as usize;
+        let length = u64_at(&mmap, MAGIC.len() + 12)? as usize;
+        let checksum = u32_at(&mmap, MAGIC.len() + 20)?;
+        let table = mmap
+            .get(offset..offset.saturating_add(length))
+            .filter(|table| crc32fast::hash(table) == checksum)
+            .ok_or(FormatError::Corrupt)?;
+        let Table { meta, blocks } = options().deserialize(table)?;
+
+        Ok(Mapped { mmap, meta, blocks })
+    }
+
+    pub fn meta(&self) -> &M {
+        &self.meta
+    }
+
+    /// Convierte los metadatos de una versión anterior a los de la actual.
+    pub fn map_meta<N>(self, f: impl FnOnce(M) -> N) -> Mapped<N> {
+        Mapped {
+            mmap: self.mmap,
+            meta: f(self.meta),
+            blocks: self.blocks,
+        }
+    }
+
+    /// Regresa el bloque guardado con la llave `key`, si existe.
+    pub fn block(&self, key: &str) -> Result<Option<&[u8]>, FormatError> {
+        let Some(&Block {
+            offset,
+            length,
+            crc,
+        }) = self.blocks.get(key)
+        else {
+            return Ok(None);
+        };
+
+        self.mmap
+            .get(offset as usize..(offset + length) as usize)
+            .filter(|block| crc32fast::hash(block) == crc)
+            .map(Some)
+            .ok_or(FormatError::Corrupt)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.blocks.contains_key(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.blocks.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_round_trip_and_validation() {
+        let path = std::env::temp_dir().join(format!("formato-{}.cdv", std::process::id()));
+
+        let mut writer = Writer::create(&path).unwrap();
+        writer.add("P04637".to_owned(), b"TP53").unwrap();
+        writer.add("Q8WZ42".to_owned(), b"TTN").unwrap();
+        writer.finish(&"metadatos".to_owned()).unwrap();
+
+        let mapped = Mapped::<String>::new(&File::open(&path).unwrap(), VERSION).unwrap();
+        assert_eq!(mapped.meta(), "metadatos");
+        assert_eq!(mapped.block("Q8WZ42").unwrap(), Some(&b"TTN"[..]));
+        assert_eq!(mapped.block("P00000").unwrap(), None);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[HEADER] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let mapped = Mapped::<String>::new(&File::open(&path).unwrap(), VERSION).unwrap();
+        assert!(matches!(mapped.block("P04637"), Err(FormatError::Corrupt)));
+
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            Mapped::<String>::new(&File::open(&path).unwrap(), VERSION),
+            Err(FormatError::IncompatibleVersion { found }) if found == VERSION + 1
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn older_versions_are_read_as_blobs() {
+        assert_eq!(read_blob(b"bincode crudo").unwrap(), b"bincode crudo");
+
+        let payload = b"contenido";
+        let mut file = MAGIC.to_vec();
+        file.extend(SINGLE_BLOB.to_le_bytes());
+        file.extend((payload.len() as u64).to_le_bytes());
+        file.extend(crc32fast::hash(payload).to_le_bytes());
+        file.extend(payload);
+        assert_eq!(read_blob(&file).unwrap(), payload);
+
+        *file.last_mut().unwrap() ^= 1;
+        assert!(matches!(read_blob(&file), Err(FormatError::Corrupt)));
+    }
+}
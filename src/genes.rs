@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, proteins::ProteinStore};
+
+static NAMES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Nombre del gen de cada proteína, por su id en uniprot: el del archivo leído con
+/// [`load_names`] o, si no se leyó ninguno, el incluido en el programa.
+pub fn names() -> &'static HashMap<String, String> {
+    NAMES.get_or_init(|| {
+        serde_json::from_str(include_str!("genes/names.json"))
+            .expect("El mapa de nombres incluido en el programa es un JSON válido")
+    })
+}
+
+/// Usa el mapa de nombres del archivo `path`, como el que escribe `build-names`, en lugar
+/// del incluido en el programa, si el archivo existe. Debe llamarse antes de consultar
+/// cualquier nombre; después ya no tiene efecto.
+pub fn load_names(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let names = serde_json::from_reader(BufReader::new(File::open(path)?))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let _ = NAMES.set(names);
+
+    Ok(())
+}
+
+/// Nombre del gen de la proteína `uniprot_id`, si se conoce.
+pub fn name(uniprot_id: &str) -> Option<&'static str> {
+    names().get(uniprot_id).map(String::as_str)
+}
+
+/// Ids de uniprot de las proteínas del gen `symbol`, sin distinguir mayúsculas de
+/// minúsculas, en orden alfabético.
+pub fn uniprot_ids(symbol: &str) -> &'static [String] {
+    static SYMBOLS: OnceLock<HashMap<String, Vec<String>>> = OnceLock::new();
+
+    let symbols = SYMBOLS.get_or_init(|| {
+        let mut symbols: HashMap<String, Vec<String>> = HashMap::new();
+        for (id, name) in names() {
+            symbols
+                .entry(name.to_uppercase())
+                .or_default()
+                .push(id.clone());
+        }
+        symbols.values_mut().for_each(|ids| ids.sort());
+
+        symbols
+    });
+
+    symbols
+        .get(&symbol.to_uppercase())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Lo que se sabe de un gen, además de su símbolo, para buscarlo. Se guarda en un archivo
+/// con un objeto JSON por línea, configurado en `gene_info`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GeneInfo {
+    pub uniprot_id: String,
+    pub symbol: String,
+    /// Nombres de la proteína, el recomendado primero.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protein_names: Vec<String>,
+    /// Otros símbolos con los que se conoce al gen.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Símbolos que tuvo el gen antes del actual.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_symbols: Vec<String>,
+    /// Ids de uniprot secundarios de la proteína.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accessions: Vec<String>,
+    /// Id del gen en HGNC, como `HGNC:11998`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hgnc_id: Option<String>,
+    /// Id del gen en Ensembl, como `ENSG00000141510`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ensembl_gene_id: Option<String>,
+    /// Ids de RefSeq, como `NM_000546`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refseq: Vec<String>,
+    /// Tipo de locus en HGNC, como `gene with protein product`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locus_type: Option<String>,
+}
+
+impl GeneInfo {
+    /// Agrega a este gen lo que se sabe en `other`, sin repetir valores. El símbolo y los
+    /// ids de `other`, si tiene, reemplazan a los actuales.
+    pub fn merge(&mut self, other: GeneInfo) {
+        fn extend(values: &mut Vec<String>, others: Vec<String>) {
+            for other in others {
+                if !values.contains(&other) {
+                    values.push(other);
+                }
+            }
+        }
+
+        if !other.symbol.is_empty() {
+            self.symbol = other.symbol;
+        }
+        extend(&mut self.protein_names, other.protein_names);
+        extend(&mut self.aliases, other.aliases);
+        extend(&mut self.previous_symbols, other.previous_symbols);
+        extend(&mut self.accessions, other.accessions);
+        extend(&mut self.refseq, other.refseq);
+        self.hgnc_id = other.hgnc_id.or(self.hgnc_id.take());
+        self.ensembl_gene_id = other.ensembl_gene_id.or(self.ensembl_gene_id.take());
+        self.locus_type = other.locus_type.or(self.locus_type.take());
+    }
+}
+
+/// Lee un archivo con un [`GeneInfo`] en JSON por línea.
+pub fn load_info(path: &Path) -> io::Result<Vec<GeneInfo>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| {
+            serde_json::from_str(&line?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// Escribe `genes` con un [`GeneInfo`] en JSON por línea, ordenados por id de uniprot.
+pub fn write_info(path: &Path, genes: &[GeneInfo]) -> io::Result<()> {
+    let mut genes: Vec<&GeneInfo> = genes.iter().collect();
+    genes.sort_by(|a, b| a.uniprot_id.cmp(&b.uniprot_id));
+
+    let mut file = BufWriter::new(File::create(path)?);
+    for gene in genes {
+        serde_json::to_writer(&mut file, gene)?;
+        writeln!(file)?;
+    }
+
+    file.flush()
+}
+
+/// Agrega `genes` al archivo `gene_info` de la configuración, junto con lo que ya tuviera,
+/// y borra el índice de búsqueda para que se reconstruya con ellos. Regresa cuántos genes
+/// tiene el archivo.
+pub fn update_info(config: &Config, genes: Vec<GeneInfo>) -> io::Result<usize> {
+    let known = match config.gene_info.exists() {
+        true => load_info(&config.gene_info)?,
+        false => Vec::new(),
+    };
+    let info = merge_info(known, genes);
+    write_info(&config.gene_info, &info)?;
+
+    if config.index.exists() {
+        std::fs::remove_dir_all(&config.index)?;
+    }
+
+    Ok(info.len())
+}
+
+/// Catálogo de genes con los nombres de las entradas de UniProtKB del archivo `proteins`,
+/// si ya se construyó, y la información del archivo `gene_info` de la configuración, si
+/// existe, que tiene prioridad.
+pub fn open(config: &Config) -> io::Result<Vec<GeneInfo>> {
+    let proteins = match ProteinStore::open(config) {
+        Ok(Some(store)) => store.gene_info(),
+        Ok(None) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    // El símbolo de HGNC del mapa incluido tiene prioridad sobre el nombre del gen en
+    // UniProtKB; si son distintos, el de UniProtKB se busca como alias.
+    let proteins = proteins
+        .into_iter()
+        .map(|mut gene| {
+            if let Some(symbol) = name(&gene.uniprot_id) {
+                let other = std::mem::take(&mut gene.symbol);
+                if other != symbol && !other.is_empty() {
+                    gene.aliases.insert(0, other);
+                }
+            }
+            gene
+        })
+        .collect();
+    let info = match config.gene_info.exists() {
+        true => load_info(&config.gene_info)?,
+        false => Vec::new(),
+    };
+
+    Ok(catalog(merge_info(proteins, info)))
+}
+
+/// Todos los genes conocidos: los del mapa de nombres incluido en el programa, con lo que
+/// se sepa de ellos en `info`. Los genes de `info` que no están en el mapa se agregan.
+pub fn catalog(info: Vec<GeneInfo>) -> Vec<GeneInfo> {
+    let known = names()
+        .iter()
+        .map(|(uniprot_id, symbol)| GeneInfo {
+            uniprot_id: uniprot_id.clone(),
+            symbol: symbol.clone(),
+            ..GeneInfo::default()
+        })
+        .collect();
+
+    merge_info(known, info)
+}
+
+/// Une dos listas de genes con [`GeneInfo::merge`], por id de uniprot, en orden
+/// alfabético. Lo que se sabe en `others` tiene prioridad.
+pub fn merge_info(genes: Vec<GeneInfo>, others: Vec<GeneInfo>) -> Vec<GeneInfo> {
+    let mut genes: HashMap<String, GeneInfo> = genes
+        .into_iter()
+        .map(|gene| (gene.uniprot_id.clone(), gene))
+        .collect();
+
+    for gene in others {
+        match genes.get_mut(&gene.uniprot_id) {
+            Some(known) => known.merge(gene),
+            None => {
+                genes.insert(gene.uniprot_id.clone(), gene);
+            }
+        }
+    }
+
+    let mut genes: Vec<GeneInfo> = genes.into_values().collect();
+    genes.sort_by(|a, b| a.uniprot_id.cmp(&b.uniprot_id));
+
+    genes
+}
@@ -0,0 +1,913 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    database::{self, IngestError, Metadata, Skipped},
+    format::{self, FormatError},
+    input,
+    liftover::{ChainError, Liftover},
+    variations::{AmClass, Variation},
+};
+
+/// Ensamble del genoma de unas coordenadas.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Assembly {
+    /// GRCh37, de la tabla `AlphaMissense_hg19.tsv.gz`.
+    Hg19,
+    /// GRCh38, de la tabla `AlphaMissense_hg38.tsv.gz`.
+    Hg38,
+}
+
+impl Assembly {
+    /// Archivos de entrada que se buscan si no se indica uno, en orden de preferencia.
+    fn inputs(&self) -> [&'static str; 2] {
+        match self {
+            Assembly::Hg19 => ["AlphaMissense_hg19.tsv.gz", "AlphaMissense_hg19.tsv"],
+            Assembly::Hg38 => ["AlphaMissense_hg38.tsv.gz", "AlphaMissense_hg38.tsv"],
+        }
+    }
+}
+
+impl std::fmt::Display for Assembly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Assembly::Hg19 => write!(f, "hg19"),
+            Assembly::Hg38 => write!(f, "hg38"),
+        }
+    }
+}
+
+impl std::str::FromStr for Assembly {
+    type Err = String;
+
+    /// Acepta los nombres de UCSC y de GRC, sin distinguir mayúsculas de minúsculas.
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "hg19" | "grch37" => Ok(Assembly::Hg19),
+            "hg38" | "grch38" => Ok(Assembly::Hg38),
+            _ => Err(format!(
+                "Ensamble desconocido: {:?} (se admiten hg19 y hg38)",
+                s
+            )),
+        }
+    }
+}
+
+/// Errores al consultar variantes genómicas.
+#[derive(Debug)]
+pub enum GenomicError {
+    /// No hay un índice del ensamble, ni forma de convertir sus coordenadas a otro.
+    Unavailable { assembly: Assembly },
+    /// Las coordenadas son de un ensamble distinto al del índice.
+    AssemblyMismatch {
+        index: Assembly,
+        requested: Assembly,
+    },
+    /// El índice está dañado.
+    Format(FormatError),
+    /// No se pudo construir el índice a partir de la tabla genómica.
+    Ingest(IngestError),
+    /// No se pudo leer el archivo de cadenas.
+    Chain(ChainError),
+}
+
+impl std::fmt::Display for GenomicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenomicError::Unavailable { assembly } => {
+                write!(f, "No hay un índice genómico para {}", assembly)
+            }
+            GenomicError::AssemblyMismatch { index, requested } => write!(
+                f,
+                "El índice genómico es de {}, pero las coordenadas son de {}",
+                index, requested
+            ),
+            GenomicError::Format(e) => write!(f, "{}", e),
+            GenomicError::Ingest(e) => write!(f, "{}", e),
+            GenomicError::Chain(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for GenomicError {}
+
+impl From<FormatError> for GenomicError {
+    fn from(e: FormatError) -> Self {
+        GenomicError::Format(e)
+    }
+}
+
+impl From<IngestError> for GenomicError {
+    fn from(e: IngestError) -> Self {
+        GenomicError::Ingest(e)
+    }
+}
+
+impl From<ChainError> for GenomicError {
+    fn from(e: ChainError) -> Self {
+        GenomicError::Chain(e)
+    }
+}
+
+/// Tamaño, en pares de bases, de la región del genoma que abarca cada bloque.
+const BIN: u32 = 100_000;
+
+/// Número de filas en AlphaMissense_hg38.tsv, para reportar el avance.
+const ROWS: usize = 71_697_556;
+
+/// Predicción de una variante genómica, como se guarda en cada bloque.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Record {
+    position: u32,
+    reference: u8,
+    alternate: u8,
+    /// Posición de la proteína en [`GenomicLayout::proteins`].
+    protein: u32,
+    variation: Variation,
+    class: AmClass,
+}
+
+/// Proteína, y transcrito, a los que afecta una variante.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+struct Protein {
+    uniprot_id: String,
+    transcript_id: String,
+}
+
+/// Metadatos que se guardan en la tabla de bloques del archivo.
+#[derive(Serialize, Deserialize)]
+struct GenomicLayout {
+    meta: Metadata,
+    /// Ensamble del genoma, tomado de la columna `genome`.
+    assembly: Assembly,
+    proteins: Vec<Protein>,
+}
+
+/// Predicción de AlphaMissense para una variante genómica.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Annotation<'a> {
+    pub uniprot_id: &'a str,
+    pub transcript_id: &'a str,
+    /// Sustitución que la variante causa en la proteína.
+    pub variation: Variation,
+    pub class: AmClass,
+}
+
+/// Columnas de la tabla genómica que se usan, según su encabezado.
+struct Columns {
+    chromosome: usize,
+    position: usize,
+    reference: usize,
+    alternate: usize,
+    genome: usize,
+    uniprot_id: usize,
+    transcript_id: usize,
+    protein_variant: usize,
+    score: usize,
+    class: usize,
+}
+
+impl Columns {
+    fn from_header(header: &str) -> Result<Self, IngestError> {
+        let names: Vec<&str> = header.trim_end().split('\t').collect();
+        let find = |name: &'static str| {
+            names
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name))
+                .ok_or(IngestError::MissingHeaderColumn { name })
+        };
+
+        Ok(Columns {
+            chromosome: find("CHROM")?,
+            position: find("POS")?,
+            reference: find("REF")?,
+            alternate: find("ALT")?,
+            genome: find("genome")?,
+            uniprot_id: find("uniprot_id")?,
+            transcript_id: find("transcript_id")?,
+            protein_variant: find("protein_variant")?,
+            score: find("am_pathogenicity")?,
+            class: find("am_class")?,
+        })
+    }
+}
+
+/// Fila de la tabla genómica.
+struct GenomicRow<'a> {
+    chromosome: String,
+    position: u32,
+    reference: u8,
+    alternate: u8,
+    genome: &'a str,
+    protein: Protein,
+    variation: Variation,
+    class: AmClass,
+}
+
+impl<'a> GenomicRow<'a> {
+    fn parse(row: &'a str, line: usize, columns: &Columns) -> Result<Self, IngestError> {
+        let values: Vec<&str> = row.trim_end_matches(['\n', '\r']).split('\t').collect();
+        let column = |index: usize| {
+            values
+                .get(index)
+                .copied()
+                .ok_or(IngestError::MissingColumn {
+                    line,
+                    column: index + 1,
+                })
+        };
+        let bad = |index: usize, text: &str| IngestError::BadCoordinate {
+            line,
+            column: index + 1,
+            text: text.to_owned(),
+        };
+        let nucleotide = |index: usize| {
+            let text = column(index)?;
+            nucleotide(text).ok_or_else(|| bad(index, text))
+        };
+
+        let position = column(columns.position)?;
+        let variant = column(columns.protein_variant)?;
+
+        Ok(GenomicRow {
+            chromosome: chromosome(column(columns.chromosome)?),
+            position: position
+                .parse()
+                .map_err(|_| bad(columns.position, position))?,
+            reference: nucleotide(columns.reference)?,
+            alternate: nucleotide(columns.alternate)?,
+            genome: column(columns.genome)?,
+            protein: Protein {
+                uniprot_id: column(columns.uniprot_id)?.to_owned(),
+                transcript_id: column(columns.transcript_id)?.to_owned(),
+            },
+            variation: variant.parse().map_err(|_| IngestError::BadVariation {
+                line,
+                column: columns.protein_variant + 1,
+                text: variant.to_owned(),
+            })?,
+            class: database::parse_class(
+                column(columns.score)?,
+                column(columns.class)?,
+                line,
+                (columns.score + 1, columns.class + 1),
+            )?,
+        })
+    }
+}
+
+/// Nombre del cromosoma con el prefijo `chr`, como en las tablas de AlphaMissense, para
+/// que `1`, `chr1` y `CHR1` sean el mismo. El mitocondrial es `chrM`.
+fn chromosome(name: &str) -> String {
+    let name = match name.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("chr") => &name[3..],
+        _ => name,
+    };
+
+    match name {
+        "MT" | "mt" | "m" => "chrM".to_owned(),
+        _ => format!("chr{}", name.to_uppercase()),
+    }
+}
+
+/// Base de un alelo de un solo nucleótido, en mayúsculas.
+fn nucleotide(allele: &str) -> Option<u8> {
+    match allele.as_bytes() {
+        [base] if b"ACGTacgt".contains(base) => Some(base.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+/// Llave del bloque con las variantes de la región de `position` en `chromosome`.
+fn key(chromosome: &str, position: u32) -> String {
+    format!("{}:{}", chromosome, position / BIN)
+}
+
+/// Índice de las predicciones de AlphaMissense por coordenada genómica de un ensamble,
+/// construido a partir de su tabla, como `AlphaMissense_hg38.tsv.gz`. Cada bloque del
+/// archivo guarda las variantes de una región de [`BIN`] pares de bases.
+pub struct GenomicIndex {
+    mapped: format::Mapped<GenomicLayout>,
+    /// Último bloque decodificado. Las variantes de un VCF vienen ordenadas, así que
+    /// casi siempre se consultan varias seguidas del mismo bloque.
+    last: Mutex<Option<(String, Arc<Vec<Record>>)>>,
+}
+
+impl GenomicIndex {
+    /// Abre el índice `path` del ensamble `assembly`. Si no existe, lo construye a partir
+    /// de la tabla genómica `input` o, si no se indica, de la primera que se encuentre. Si
+    /// tampoco existe, regresa `None`. Es un error que el índice sea de otro ensamble.
+    pub fn open(
+        path: &Path,
+        input: Option<&Path>,
+        assembly: Assembly,
+    ) -> Result<Option<Self>, GenomicError> {
+        if !path.exists() {
+            let Some(input) = GenomicIndex::input(input, assembly) else {
+                return Ok(None);
+            };
+            GenomicIndex::build(input, path, assembly, false)?;
+        }
+
+        let index = GenomicIndex::load(path)?;
+        if index.assembly() != assembly {
+            return Err(GenomicError::AssemblyMismatch {
+                index: index.assembly(),
+                requested: assembly,
+            });
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Regresa `input` o, si no se indica, la primera de las tablas genómicas de
+    /// `assembly` que exista en el directorio actual.
+    pub fn input(input: Option<&Path>, assembly: Assembly) -> Option<&Path> {
+        input::find(input, &assembly.inputs())
+    }
+
+    /// Abre el índice sin leerlo completo.
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        Ok(GenomicIndex {
+            mapped: format::Mapped::new(&File::open(path)?, format::VERSION)?,
+            last: Mutex::new(None),
+        })
+    }
+
+    /// Construye el índice a partir de la tabla genómica `input` y lo guarda en `output`.
+    /// La tabla es demasiado grande para tenerla en memoria, así que se escribe conforme
+    /// se lee; por eso debe estar ordenada por cromosoma y posición, como se publica.
+    ///
+    /// Todas las filas deben ser del ensamble `assembly`, según su columna `genome`; una
+    /// fila de otro ensamble es un error aun con `lenient`, con el que el resto de las
+    /// filas inválidas se omiten y al final se imprime un resumen.
+    pub fn build(
+        input: &Path,
+        output: &Path,
+        assembly: Assembly,
+        lenient: bool,
+    ) -> Result<(), IngestError> {
+        let (mut file, digest) = input::open_with_digest(input)?;
+
+        // El encabezado son líneas de comentario; la última tiene los nombres de las columnas.
+        let mut header = Vec::new();
+        let mut line = String::new();
+        let mut number = 0;
+        loop {
+            line.clear();
+            if file.read_line(&mut line)? == 0 {
+                break;
+            }
+            number += 1;
+            match line.strip_prefix('#') {
+                Some(comment) => header.push(comment.trim().to_owned()),
+                None => break,
+            }
+        }
+        let columns = Columns::from_header(
+            &header
+                .pop()
+                .ok_or(IngestError::MissingHeaderColumn { name: "CHROM" })?,
+        )?;
+
+        let temporary = output.with_extension("tmp");
+        let mut writer = format::Writer::create(&temporary)?;
+        let mut proteins: HashMap<Protein, u32> = HashMap::new();
+        let mut skipped = Skipped::default();
+
+        let mut written = HashSet::new();
+        let mut current = String::new();
+        let mut block = Vec::new();
+        let mut write = |key: &str, block: &mut Vec<Record>| -> Result<(), IngestError> {
+            if block.is_empty() {
+                return Ok(());
+            }
+            block.sort_by_key(|record| record.position);
+            let encoded = format::options()
+                .serialize(block)
+                .map_err(|e| IngestError::Output(e.into()))?;
+            writer.add(key.to_owned(), &encoded)?;
+            block.clear();
+
+            Ok(())
+        };
+
+        let first = number;
+        while !line.is_empty() {
+            match GenomicRow::parse(&line, number, &columns) {
+                Ok(row) if row.genome.parse() != Ok(assembly) => {
+                    return Err(IngestError::WrongAssembly {
+                        line: number,
+                        expected: assembly.to_string(),
+                        found: row.genome.to_owned(),
+                    });
+                }
+                Ok(row) => {
+                    let key = key(&row.chromosome, row.position);
+                    if key != current {
+                        write(&current, &mut block)?;
+                        if !written.insert(key.clone()) {
+                            return Err(IngestError::Unsorted { line: number });
+                        }
+                        current = key;
+                    }
+
+                    let next = proteins.len() as u32;
+                    block.push(Record {
+                        position: row.position,
+                        reference: row.reference,
+                        alternate: row.alternate,
+                        protein: *proteins.entry(row.protein).or_insert(next),
+                        variation: row.variation,
+                        class: row.class,
+                    });
+                }
+                Err(e) if lenient => skipped.push(e),
+                Err(e) => return Err(e),
+            }
+
+            line.clear();
+            if file.read_line(&mut line)? == 0 {
+                break;
+            }
+            number += 1;
+            database::percentage_completed(
+                "Indexando variantes",
+                number - first - 1,
+                number - first,
+                ROWS,
+            );
+        }
+        write(&current, &mut block)?;
+
+        if lenient {
+            skipped.summary();
+        }
+
+        let mut proteins: Vec<_> = proteins.into_iter().collect();
+        proteins.sort_by_key(|(_, index)| *index);
+        let source = input
+            .file_name()
+            .unwrap_or(input.as_os_str())
+            .to_string_lossy();
+        writer
+            .finish(&GenomicLayout {
+                meta: Metadata::from_header(&source, header, digest.hex()),
+                assembly,
+                proteins: proteins.into_iter().map(|(protein, _)| protein).collect(),
+            })
+            .map_err(IngestError::Output)?;
+        std::fs::rename(temporary, output)?;
+
+        Ok(())
+    }
+
+    /// Datos de la publicación de AlphaMissense con la que se construyó.
+    pub fn meta(&self) -> &Metadata {
+        &self.mapped.meta().meta
+    }
+
+    /// Ensamble del genoma de las coordenadas.
+    pub fn assembly(&self) -> Assembly {
+        self.mapped.meta().assembly
+    }
+
+    /// Pares de id de uniprot y transcrito de las proteínas del índice.
+    pub fn transcripts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.mapped
+            .meta()
+            .proteins
+            .iter()
+            .map(|protein| (protein.uniprot_id.as_str(), protein.transcript_id.as_str()))
+    }
+
+    /// Variantes de la región de `position` en `chromosome`, ordenadas por posición.
+    fn block(&self, chromosome: &str, position: u32) -> Result<Arc<Vec<Record>>, FormatError> {
+        let key = key(chromosome, position);
+        let mut last = self
+            .last
+            .lock()
+            .expect("Ningún hilo entra en pánico con el candado");
+
+        match &*last {
+            Some((cached, records)) if *cached == key => Ok(records.clone()),
+            _ => {
+                let records: Arc<Vec<Record>> = Arc::new(match self.mapped.block(&key)? {
+                    Some(block) => format::options().deserialize(block)?,
+                    None => Vec::new(),
+                });
+                *last = Some((key, records.clone()));
+
+                Ok(records)
+            }
+        }
+    }
+
+    /// Predicciones para la sustitución de `reference` por `alternate` en `position`
+    /// (desde 1) de `chromosome`, en coordenadas de `assembly`, que debe ser el ensamble
+    /// del índice. El cromosoma puede llevar el prefijo `chr` o no. Solo hay predicciones
+    /// para variantes de un solo nucleótido que cambian un aminoácido; para el resto se
+    /// regresa una lista vacía. Una variante puede afectar a más de una proteína.
+    pub fn lookup(
+        &self,
+        assembly: Assembly,
+        chromosome: &str,
+        position: u32,
+        reference: &str,
+        alternate: &str,
+    ) -> Result<Vec<Annotation<'_>>, GenomicError> {
+        if assembly != self.assembly() {
+            return Err(GenomicError::AssemblyMismatch {
+                index: self.assembly(),
+                requested: assembly,
+            });
+        }
+
+        let (Some(reference), Some(alternate)) = (nucleotide(reference), nucleotide(alternate))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let records = self.block(&self::chromosome(chromosome), position)?;
+        let start = records.partition_point(|record| record.position < position);
+        let proteins = &self.mapped.meta().proteins;
+
+        Ok(records[start..]
+            .iter()
+            .take_while(|record| record.position == position)
+            .filter(|record| record.reference == reference && record.alternate == alternate)
+            .map(|record| {
+                let protein = &proteins[record.protein as usize];
+                Annotation {
+                    uniprot_id: &protein.uniprot_id,
+                    transcript_id: &protein.transcript_id,
+                    variation: record.variation,
+                    class: record.class,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Índices genómicos de los ensambles disponibles. Si no hay índice de hg19 pero sí un
+/// archivo de cadenas de hg19 a hg38, las coordenadas de hg19 se convierten y se buscan en
+/// el índice de hg38.
+pub struct Genome {
+    hg38: Option<GenomicIndex>,
+    hg19: Option<GenomicIndex>,
+    liftover: Option<Liftover>,
+}
+
+impl Genome {
+    pub fn new(
+        hg38: Option<GenomicIndex>,
+        hg19: Option<GenomicIndex>,
+        liftover: Option<Liftover>,
+    ) -> Self {
+        Genome {
+            hg38,
+            hg19,
+            liftover,
+        }
+    }
+
+    /// Abre los índices indicados en la configuración, construyendo los que falten si se
+    /// encuentra su tabla, y lee el archivo de cadenas si se indica uno.
+    pub fn open(config: &Config) -> Result<Self, GenomicError> {
+        Ok(Genome {
+            hg38: GenomicIndex::open(&config.hg38, config.hg38_input.as_deref(), Assembly::Hg38)?,
+            hg19: GenomicIndex::open(&config.hg19, config.hg19_input.as_deref(), Assembly::Hg19)?,
+            liftover: config.chain.as_deref().map(Liftover::load).transpose()?,
+        })
+    }
+
+    /// Como [`GenomicIndex::transcripts`], del índice de hg38 o, si no hay, del de hg19.
+    pub fn transcripts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.hg38
+            .as_ref()
+            .or(self.hg19.as_ref())
+            .into_iter()
+            .flat_map(GenomicIndex::transcripts)
+    }
+
+    /// Ensambles para los que se pueden consultar variantes.
+    pub fn assemblies(&self) -> Vec<Assembly> {
+        let mut assemblies = Vec::new();
+        if self.hg19.is_some() || (self.hg38.is_some() && self.liftover.is_some()) {
+            assemblies.push(Assembly::Hg19);
+        }
+        if self.hg38.is_some() {
+            assemblies.push(Assembly::Hg38);
+        }
+
+        assemblies
+    }
+
+    /// Como [`GenomicIndex::lookup`], con el índice de `assembly`. Las posiciones de hg19
+    /// que no están en el archivo de cadenas no tienen predicciones.
+    pub fn lookup(
+        &self,
+        assembly: Assembly,
+        chromosome: &str,
+        position: u32,
+        reference: &str,
+        alternate: &str,
+    ) -> Result<Vec<Annotation<'_>>, GenomicError> {
+        let unavailable = GenomicError::Unavailable { assembly };
+
+        match (assembly, &self.hg19, &self.hg38, &self.liftover) {
+            (Assembly::Hg19, Some(index), _, _) | (Assembly::Hg38, _, Some(index), _) => {
+                index.lookup(assembly, chromosome, position, reference, alternate)
+            }
+            (Assembly::Hg19, None, Some(index), Some(liftover)) => {
+                let Some(lifted) = liftover.lift(&self::chromosome(chromosome), position) else {
+                    return Ok(Vec::new());
+                };
+                let allele = |allele: &str| match lifted.reverse {
+                    true => complement(allele),
+                    false => allele.to_owned(),
+                };
+
+                index.lookup(
+                    Assembly::Hg38,
+                    &lifted.chromosome,
+                    lifted.position,
+                    &allele(reference),
+                    &allele(alternate),
+                )
+            }
+            _ => Err(unavailable),
+        }
+    }
+}
+
+/// Alelo de la cadena complementaria.
+fn complement(allele: &str) -> String {
+    allele
+        .chars()
+        .rev()
+        .map(|base| match base.to_ascii_uppercase() {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            'T' => 'A',
+            other => other,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{BufWriter, Write};
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Tabla genómica con algunas variantes de TP53 y BRCA1, con el formato de
+    /// `AlphaMissense_hg38.tsv.gz`.
+    pub(crate) fn synthetic_table(name: &str, rows: &[&str]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.tsv", name, std::process::id()));
+        let mut file = BufWriter::new(File::create(&path).unwrap());
+
+        writeln!(file, "# Copyright 2023 DeepMind Technologies Limited").unwrap();
+        writeln!(file, "#").unwrap();
+        writeln!(file, "# Licensed under CC BY-NC-SA 4.0 license").unwrap();
+        writeln!(
+            file,
+            "#CHROM\tPOS\tREF\tALT\tgenome\tuniprot_id\ttranscript_id\t\
+             protein_variant\tam_pathogenicity\tam_class"
+        )
+        .unwrap();
+        for row in rows {
+            writeln!(file, "{}", row.replace(' ', "\t")).unwrap();
+        }
+
+        path
+    }
+
+    pub(crate) const ROWS: [&str; 5] = [
+        "chr17 7675088 C T hg38 P04637 ENST00000269305.9 R175H 0.9977 pathogenic",
+        "chr17 7675088 C A hg38 P04637 ENST00000269305.9 R175L 0.9912 pathogenic",
+        "chr17 7675089 G A hg38 P04637 ENST00000269305.9 R175C 0.3001 benign",
+        "chr17 43045712 T C hg38 P38398 ENST00000357654.9 K1859E 0.4 ambiguous",
+        "chrX 100000 A G hg38 P00000 ENST00000000000.1 M1V 0.5 ambiguous",
+    ];
+
+    #[test]
+    fn genomic_lookups() {
+        let input = synthetic_table("genomica", &ROWS);
+        let output = input.with_extension("cdv");
+        GenomicIndex::build(&input, &output, Assembly::Hg38, false).unwrap();
+        let index = GenomicIndex::load(&output).unwrap();
+
+        assert_eq!(index.assembly(), Assembly::Hg38);
+        assert_eq!(
+            index.meta().license.as_deref(),
+            Some("Licensed under CC BY-NC-SA 4.0 license")
+        );
+
+        let annotations = index
+            .lookup(Assembly::Hg38, "17", 7675088, "c", "T")
+            .unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].uniprot_id, "P04637");
+        assert_eq!(annotations[0].transcript_id, "ENST00000269305.9");
+        assert_eq!(annotations[0].variation.to_string(), "R175H");
+        assert_eq!(annotations[0].class, AmClass::Pathogenic(0.9977));
+
+        assert_eq!(
+            index
+                .lookup(Assembly::Hg38, "chr17", 43045712, "T", "C")
+                .unwrap()[0]
+                .uniprot_id,
+            "P38398"
+        );
+        assert_eq!(
+            index
+                .lookup(Assembly::Hg38, "X", 100000, "A", "G")
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(index
+            .lookup(Assembly::Hg38, "chr17", 7675088, "C", "G")
+            .unwrap()
+            .is_empty());
+        assert!(index
+            .lookup(Assembly::Hg38, "chr17", 7675088, "CA", "T")
+            .unwrap()
+            .is_empty());
+        assert!(index
+            .lookup(Assembly::Hg38, "chr2", 7675088, "C", "T")
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            index.lookup(Assembly::Hg19, "17", 7675088, "C", "T"),
+            Err(GenomicError::AssemblyMismatch {
+                index: Assembly::Hg38,
+                requested: Assembly::Hg19
+            })
+        ));
+
+        // Los errores al construir el índice o al leer las cadenas se regresan.
+        let missing = input.with_extension("hg19.cdv");
+        assert!(matches!(
+            GenomicIndex::open(&missing, Some(&input), Assembly::Hg19),
+            Err(GenomicError::Ingest(IngestError::WrongAssembly { .. }))
+        ));
+        let _ = std::fs::remove_file(missing.with_extension("tmp"));
+        let config = Config {
+            hg38: output.clone(),
+            hg19: missing,
+            chain: Some(input.with_extension("chain")),
+            ..Config::default()
+        };
+        assert!(matches!(
+            Genome::open(&config),
+            Err(GenomicError::Chain(ChainError::Io(_)))
+        ));
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn unsorted_and_bad_rows() {
+        let input = synthetic_table("desordenada", &[ROWS[0], ROWS[3], ROWS[2]]);
+        let output = input.with_extension("cdv");
+        assert!(matches!(
+            GenomicIndex::build(&input, &output, Assembly::Hg38, false),
+            Err(IngestError::Unsorted { line: 7 })
+        ));
+
+        let bad = "chr17 7675088 CT T hg38 P04637 ENST00000269305.9 R175H 0.9977 pathogenic";
+        let input = synthetic_table("desordenada", &[ROWS[0], bad, ROWS[2]]);
+        assert!(matches!(
+            GenomicIndex::build(&input, &output, Assembly::Hg38, false),
+            Err(IngestError::BadCoordinate {
+                line: 6,
+                column: 3,
+                ..
+            })
+        ));
+        GenomicIndex::build(&input, &output, Assembly::Hg38, true).unwrap();
+        let index = GenomicIndex::load(&output).unwrap();
+        assert_eq!(
+            index
+                .lookup(Assembly::Hg38, "17", 7675089, "G", "A")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn assemblies_are_not_mixed() {
+        let input = synthetic_table("ensambles", &ROWS);
+        let output = input.with_extension("cdv");
+        assert!(matches!(
+            GenomicIndex::build(&input, &output, Assembly::Hg19, false),
+            Err(IngestError::WrongAssembly { line: 5, .. })
+        ));
+
+        let hg19 = "chr17 7675088 C T hg19 P04637 ENST00000269305.9 R175H 0.9977 pathogenic";
+        let input = synthetic_table("ensambles", &[ROWS[0], hg19]);
+        assert!(matches!(
+            GenomicIndex::build(&input, &output, Assembly::Hg38, true),
+            Err(IngestError::WrongAssembly { line: 6, .. })
+        ));
+
+        let input = synthetic_table("ensambles", &ROWS);
+        GenomicIndex::build(&input, &output, Assembly::Hg38, false).unwrap();
+        assert!(matches!(
+            GenomicIndex::open(&output, None, Assembly::Hg19),
+            Err(GenomicError::AssemblyMismatch {
+                index: Assembly::Hg38,
+                requested: Assembly::Hg19
+            })
+        ));
+
+        // Los errores al construir el índice o al leer las cadenas se regresan.
+        let missing = input.with_extension("hg19.cdv");
+        assert!(matches!(
+            GenomicIndex::open(&missing, Some(&input), Assembly::Hg19),
+            Err(GenomicError::Ingest(IngestError::WrongAssembly { .. }))
+        ));
+        let _ = std::fs::remove_file(missing.with_extension("tmp"));
+        let config = Config {
+            hg38: output.clone(),
+            hg19: missing,
+            chain: Some(input.with_extension("chain")),
+            ..Config::default()
+        };
+        assert!(matches!(
+            Genome::open(&config),
+            Err(GenomicError::Chain(ChainError::Io(_)))
+        ));
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn hg19_coordinates_are_lifted_over() {
+        let input = synthetic_table("cadenas", &ROWS);
+        let output = input.with_extension("cdv");
+        GenomicIndex::build(&input, &output, Assembly::Hg38, false).unwrap();
+
+        // chr17 de hg19 se alinea con chr17 de hg38 con 24,912 bases de diferencia; un
+        // segmento de chr2 se alinea invertido con la región de R175 de TP53.
+        let chain = input.with_extension("chain");
+        std::fs::write(
+            &chain,
+            "chain 10 chr17 81195210 + 7000000 7800000 chr17 83257441 + 6975088 7775088 1\n\
+             800000\n\n\
+             chain 5 chr2 243199373 + 0 100 chr17 83257441 - 75582303 75582403 2\n\
+             100\n",
+        )
+        .unwrap();
+
+        let genome = Genome::new(
+            Some(GenomicIndex::load(&output).unwrap()),
+            None,
+            Some(Liftover::load(&chain).unwrap()),
+        );
+        assert_eq!(genome.assemblies(), [Assembly::Hg19, Assembly::Hg38]);
+
+        let variant = |assembly, chromosome, position, reference, alternate| {
+            genome
+                .lookup(assembly, chromosome, position, reference, alternate)
+                .unwrap()
+                .iter()
+                .map(|annotation| annotation.variation.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(variant(Assembly::Hg38, "17", 7675088, "C", "T"), ["R175H"]);
+        assert_eq!(variant(Assembly::Hg19, "17", 7700000, "C", "T"), ["R175H"]);
+        assert_eq!(variant(Assembly::Hg19, "2", 51, "G", "A"), ["R175H"]);
+        assert!(variant(Assembly::Hg19, "17", 7675088, "C", "T").is_empty());
+        assert!(variant(Assembly::Hg19, "3", 51, "G", "A").is_empty());
+
+        let hg38 = Genome::new(Some(GenomicIndex::load(&output).unwrap()), None, None);
+        assert_eq!(hg38.assemblies(), [Assembly::Hg38]);
+        assert!(matches!(
+            hg38.lookup(Assembly::Hg19, "17", 7700000, "C", "T"),
+            Err(GenomicError::Unavailable { .. })
+        ));
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+        std::fs::remove_file(chain).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aminoacids::{AminoAcid, AminoAcidMap},
+    database::{GeneVariations, QueryError},
+};
+
+/// Qué parte de la proteína incluir en un [`Heatmap`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeatmapOptions {
+    /// Primera posición, contando desde 1. Si no se indica, la primera de la proteína.
+    pub start: Option<u16>,
+    /// Última posición, incluida. Si no se indica, la última de la proteína.
+    pub end: Option<u16>,
+    /// Número máximo de filas. Si el intervalo tiene más posiciones, cada fila promedia
+    /// varias posiciones consecutivas.
+    pub rows: Option<usize>,
+}
+
+/// Patogenicidad de todas las sustituciones de un intervalo de la proteína, en una matriz
+/// de posiciones por aminoácidos.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    pub start: u16,
+    pub end: u16,
+    /// Posiciones que abarca cada fila de `scores`: 1 si no se redujo la matriz.
+    pub step: u16,
+    /// Aminoácido de cada columna, en el orden de [`AminoAcid::SINGLE_LETTER_CODE`].
+    pub columns: String,
+    /// Aminoácido de referencia de cada posición del intervalo, sin reducir, o `-` si la
+    /// posición no tiene predicciones.
+    pub reference: String,
+    /// Una fila de 20 patogenicidades por cada `step` posiciones. Las sustituciones sin
+    /// predicción, incluida la del aminoácido de referencia, son `null`.
+    pub scores: Vec<[Option<f32>; 20]>,
+}
+
+impl Heatmap {
+    /// Construye la matriz de `gene` en el intervalo de `options`. El final se recorta a la
+    /// longitud de la proteína; si el intervalo queda vacío, es un error.
+    pub fn new(gene: &GeneVariations, options: &HeatmapOptions) -> Result<Self, QueryError> {
+        let start = options.start.unwrap_or(1).max(1);
+        let end = options.end.unwrap_or(gene.len()).min(gene.len());
+        if start > end {
+            return Err(QueryError::UnknownPosition { position: start });
+        }
+
+        let length = (end - start) as usize + 1;
+        let step = match options.rows {
+            Some(rows) if rows > 0 => length.div_ceil(rows).max(1),
+            _ => 1,
+        };
+
+        let AminoAcidMap(letters) = AminoAcid::SINGLE_LETTER_CODE;
+        let reference = (start..=end)
+            .map(|position| match gene.reference(position) {
+                Some(base) => AminoAcid::SINGLE_LETTER_CODE[base],
+                None => '-',
+            })
+            .collect();
+
+        let positions: Vec<u16> = (start..=end).collect();
+        let scores = positions
+            .chunks(step)
+            .map(|positions| {
+                AminoAcid::ALL.map(|variant| {
+                    let scores: Vec<f32> = positions
+                        .iter()
+                        .filter_map(|&position| gene.pathogenicity(position, variant))
+                        .filter_map(|class| class.score())
+                        .collect();
+
+                    (!scores.is_empty()).then(|| scores.iter().sum::<f32>() / scores.len() as f32)
+                })
+            })
+            .collect();
+
+        Ok(Heatmap {
+            start,
+            end,
+            step: step as u16,
+            columns: letters.iter().collect(),
+            reference,
+            scores,
+        })
+    }
+
+    /// Primera posición que abarca la fila `row`.
+    pub fn position(&self, row: usize) -> u16 {
+        self.start + (row * self.step as usize) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{AmClass, Variation};
+
+    #[test]
+    fn heatmaps_follow_the_single_letter_code() {
+        let gene: GeneVariations = [
+            ("M1R", AmClass::Benign(0.1)),
+            ("K2R", AmClass::Pathogenic(0.9)),
+            ("K2A", AmClass::Ambiguous(0.4)),
+            ("L4R", AmClass::Pathogenic(0.7)),
+        ]
+        .into_iter()
+        .map(|(variant, class)| (Variation::from_str(variant).unwrap(), class))
+        .collect();
+
+        let heatmap = Heatmap::new(&gene, &HeatmapOptions::default()).unwrap();
+        assert_eq!(heatmap.columns, "ARNDCEQGHILKMFPSTWYV");
+        assert_eq!(heatmap.reference, "MK-L");
+        assert_eq!(heatmap.scores.len(), 4);
+        assert_eq!(heatmap.scores[1][0], Some(0.4));
+        assert_eq!(heatmap.scores[1][1], Some(0.9));
+        assert_eq!(heatmap.scores[1][11], None);
+        assert_eq!(heatmap.scores[2], [None; 20]);
+
+        let options = HeatmapOptions {
+            start: Some(2),
+            end: Some(100),
+            rows: Some(2),
+        };
+        let heatmap = Heatmap::new(&gene, &options).unwrap();
+        assert_eq!((heatmap.start, heatmap.end, heatmap.step), (2, 4, 2));
+        assert_eq!(heatmap.reference, "K-L");
+        assert_eq!(heatmap.position(1), 4);
+        assert_eq!(heatmap.scores[0][1], Some(0.9));
+        assert_eq!(heatmap.scores[1][1], Some(0.7));
+
+        let options = HeatmapOptions {
+            start: Some(5),
+            ..HeatmapOptions::default()
+        };
+        assert_eq!(
+            Heatmap::new(&gene, &options),
+            Err(QueryError::UnknownPosition { position: 5 })
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::{database::IngestError, genes::GeneInfo, input, DataBase};
+
+/// Gen aprobado de la tabla completa de HGNC (`hgnc_complete_set.txt`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HgncGene {
+    pub hgnc_id: String,
+    pub symbol: String,
+    pub name: String,
+    pub locus_type: String,
+    pub aliases: Vec<String>,
+    pub previous_symbols: Vec<String>,
+    pub ensembl_gene_id: Option<String>,
+    pub refseq: Vec<String>,
+    pub uniprot_ids: Vec<String>,
+}
+
+/// Columnas de la tabla que se leen, por su nombre en el encabezado.
+struct Columns {
+    hgnc_id: usize,
+    symbol: usize,
+    name: Option<usize>,
+    status: Option<usize>,
+    locus_type: Option<usize>,
+    aliases: Option<usize>,
+    previous_symbols: Option<usize>,
+    ensembl_gene_id: Option<usize>,
+    refseq: Option<usize>,
+    uniprot_ids: usize,
+}
+
+impl Columns {
+    fn from_header(header: &str) -> Result<Self, IngestError> {
+        let names: Vec<&str> = header.split('\t').map(str::trim).collect();
+        let find = |name: &str| names.iter().position(|column| *column == name);
+        let require =
+            |name: &'static str| find(name).ok_or(IngestError::MissingHeaderColumn { name });
+
+        Ok(Columns {
+            hgnc_id: require("hgnc_id")?,
+            symbol: require("symbol")?,
+            name: find("name"),
+            status: find("status"),
+            locus_type: find("locus_type"),
+            aliases: find("alias_symbol"),
+            previous_symbols: find("prev_symbol"),
+            ensembl_gene_id: find("ensembl_gene_id"),
+            refseq: find("refseq_accession"),
+            uniprot_ids: require("uniprot_ids")?,
+        })
+    }
+}
+
+/// Valores de una celda: HGNC separa los valores múltiples con `|` y a veces los pone
+/// entre comillas.
+fn values(cell: &str) -> Vec<String> {
+    cell.trim()
+        .trim_matches('"')
+        .split('|')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Genes aprobados de HGNC, con los ids de uniprot de sus proteínas.
+#[derive(Debug, Default)]
+pub struct Hgnc {
+    genes: Vec<HgncGene>,
+}
+
+impl Hgnc {
+    /// Lee la tabla completa de HGNC, comprimida o no, descargada de
+    /// `https://www.genenames.org/download/archive/`.
+    pub fn load(path: &Path) -> Result<Self, IngestError> {
+        Hgnc::read(input::decompress(File::open(path)?)?)
+    }
+
+    fn read(file: impl BufRead) -> Result<Self, IngestError> {
+        let mut lines = file.lines().enumerate();
+        let columns = match lines.next() {
+            Some((_, header)) => Columns::from_header(&header?)?,
+            None => return Ok(Hgnc::default()),
+        };
+
+        let mut genes = Vec::new();
+        for (number, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let cells: Vec<&str> = line.split('\t').collect();
+            let cell = |index: usize| cells.get(index).copied().unwrap_or_default();
+            let optional = |index: Option<usize>| index.map(cell).unwrap_or_default();
+            if cells.len() <= columns.symbol.max(columns.hgnc_id) {
+                return Err(IngestError::MissingColumn {
+                    line: number + 1,
+                    column: columns.symbol.max(columns.hgnc_id) + 1,
+                });
+            }
+            // Los símbolos retirados no tienen proteína y pueden repetir símbolos vigentes.
+            if !matches!(optional(columns.status).trim(), "" | "Approved") {
+                continue;
+            }
+
+            genes.push(HgncGene {
+                hgnc_id: cell(columns.hgnc_id).trim().to_owned(),
+                symbol: cell(columns.symbol).trim().to_owned(),
+                name: optional(columns.name).trim().trim_matches('"').to_owned(),
+                locus_type: optional(columns.locus_type).trim().to_owned(),
+                aliases: values(optional(columns.aliases)),
+                previous_symbols: values(optional(columns.previous_symbols)),
+                ensembl_gene_id: values(optional(columns.ensembl_gene_id)).into_iter().next(),
+                refseq: values(optional(columns.refseq)),
+                uniprot_ids: values(cell(columns.uniprot_ids)),
+            });
+        }
+
+        Ok(Hgnc { genes })
+    }
+
+    pub fn genes(&self) -> &[HgncGene] {
+        &self.genes
+    }
+
+    /// Símbolo del gen de cada id de uniprot, como `src/genes/names.json`. Si una proteína
+    /// aparece en varios genes, se queda con el de menor id de HGNC, para que el resultado no
+    /// dependa del orden de la tabla.
+    pub fn names(&self) -> BTreeMap<String, String> {
+        let mut owners: HashMap<&str, &HgncGene> = HashMap::new();
+        for gene in &self.genes {
+            for uniprot_id in &gene.uniprot_ids {
+                let owner = owners.entry(uniprot_id).or_insert(gene);
+                if hgnc_number(&gene.hgnc_id) < hgnc_number(&owner.hgnc_id) {
+                    *owner = gene;
+                }
+            }
+        }
+
+        owners
+            .into_iter()
+            .map(|(uniprot_id, gene)| (uniprot_id.to_owned(), gene.symbol.clone()))
+            .collect()
+    }
+
+    /// Lo que HGNC sabe de cada proteína de [`Hgnc::names`], para el buscador.
+    pub fn gene_info(&self) -> Vec<GeneInfo> {
+        let names = self.names();
+        let genes: HashMap<&str, &HgncGene> = self
+            .genes
+            .iter()
+            .map(|gene| (gene.symbol.as_str(), gene))
+            .collect();
+
+        names
+            .iter()
+            .filter_map(|(uniprot_id, symbol)| {
+                let gene = genes.get(symbol.as_str())?;
+
+                Some(GeneInfo {
+                    uniprot_id: uniprot_id.clone(),
+                    symbol: gene.symbol.clone(),
+                    aliases: gene.aliases.clone(),
+                    previous_symbols: gene.previous_symbols.clone(),
+                    hgnc_id: Some(gene.hgnc_id.clone()),
+                    ensembl_gene_id: gene.ensembl_gene_id.clone(),
+                    refseq: gene.refseq.clone(),
+                    locus_type: (!gene.locus_type.is_empty()).then(|| gene.locus_type.clone()),
+                    ..GeneInfo::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Ids de uniprot de `database` que no están en ningún gen de HGNC, en orden alfabético.
+    pub fn unmapped<'a>(&self, database: &'a DataBase) -> Vec<&'a str> {
+        let names = self.names();
+        let mut unmapped: Vec<&str> = database
+            .ids()
+            .filter(|uniprot_id| !names.contains_key(*uniprot_id))
+            .collect();
+        unmapped.sort_unstable();
+
+        unmapped
+    }
+}
+
+/// Número de un id de HGNC, como 11998 en `HGNC:11998`.
+fn hgnc_number(hgnc_id: &str) -> u32 {
+    hgnc_id
+        .trim_start_matches("HGNC:")
+        .parse()
+        .unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_complete_set() {
+        let hgnc = Hgnc::read(
+            "hgnc_id\tsymbol\tname\tlocus_type\tstatus\talias_symbol\tprev_symbol\t\
+             ensembl_gene_id\trefseq_accession\tuniprot_ids\n\
+             HGNC:11998\tTP53\ttumor protein p53\tgene with protein product\tApproved\t\
+             \"LFS1|p53\"\t\tENSG00000141510\tNM_000546\tP04637\n\
+             HGNC:12000\tTP53X\tshared\tgene with protein product\tApproved\t\t\t\t\tP04637|Q00001\n\
+             HGNC:5\tOLD\told\tunknown\tEntry Withdrawn\t\t\t\t\tQ99999\n\
+             HGNC:7\tLINC1\tlong non-coding\tRNA, long non-coding\tApproved\t\tLNC1\t\t\t\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(hgnc.genes().len(), 3);
+
+        let names = hgnc.names();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["P04637"], "TP53");
+        assert_eq!(names["Q00001"], "TP53X");
+
+        let info = hgnc.gene_info();
+        let tp53 = info
+            .iter()
+            .find(|gene| gene.uniprot_id == "P04637")
+            .unwrap();
+        assert_eq!(tp53.aliases, ["LFS1", "p53"]);
+        assert_eq!(tp53.ensembl_gene_id.as_deref(), Some("ENSG00000141510"));
+        assert_eq!(tp53.refseq, ["NM_000546"]);
+        assert_eq!(
+            tp53.locus_type.as_deref(),
+            Some("gene with protein product")
+        );
+
+        assert!(matches!(
+            Hgnc::read("symbol\tuniprot_ids\n".as_bytes()),
+            Err(IngestError::MissingHeaderColumn { name: "hgnc_id" })
+        ));
+    }
+}
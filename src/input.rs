@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use flate2::read::MultiGzDecoder;
+use sha2::{Digest as _, Sha256};
+
+/// Bytes con los que inicia todo archivo gzip (incluidos los bgzip).
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Tamaño del buffer de lectura. Los archivos de AlphaMissense pesan varios gigabytes.
+const BUFFER: usize = 1 << 20;
+
+/// Abre un archivo de texto para leerlo secuencialmente.
+///
+/// La compresión se detecta por los primeros bytes del archivo y no por su extensión:
+/// si el archivo es gzip se descomprime al vuelo, si no, se lee tal cual. Además se
+/// calcula el SHA-256 del archivo tal como está en disco conforme se va leyendo.
+pub fn open_with_digest(path: impl AsRef<Path>) -> io::Result<(Box<dyn BufRead + Send>, Digest)> {
+    let digest = Digest(Arc::new(Mutex::new(Sha256::new())));
+    let file = Hashing {
+        inner: File::open(path)?,
+        hasher: digest.0.clone(),
+    };
+
+    Ok((decompress(file)?, digest))
+}
+
+/// Lee `file`, descomprimiéndolo si es gzip o bgzip. Sirve para lectores que no son
+/// archivos, como la entrada estándar o el cuerpo de una petición.
+pub fn decompress<R: Read + Send + 'static>(file: R) -> io::Result<Box<dyn BufRead + Send>> {
+    let mut file = BufReader::with_capacity(BUFFER, file);
+
+    if file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::with_capacity(
+            BUFFER,
+            MultiGzDecoder::new(file),
+        )))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Regresa `explicit` o, si no se indica, el primero de los archivos `candidates` que
+/// exista en el directorio actual.
+pub fn find<'a>(explicit: Option<&'a Path>, candidates: &[&'a str]) -> Option<&'a Path> {
+    explicit.or_else(|| {
+        candidates
+            .iter()
+            .map(|candidate| Path::new(*candidate))
+            .find(|path| path.exists())
+    })
+}
+
+/// SHA-256 de un archivo abierto con [`open_with_digest`].
+pub struct Digest(Arc<Mutex<Sha256>>);
+
+impl Digest {
+    /// Regresa el SHA-256, en hexadecimal, de todo lo leído hasta ahora. Solo corresponde
+    /// al archivo completo si se leyó hasta el final.
+    pub fn hex(&self) -> String {
+        let hasher = self
+            .0
+            .lock()
+            .expect("Ningún hilo entra en pánico con el candado");
+
+        hasher
+            .clone()
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+/// Lector que alimenta al SHA-256 con todo lo que lee.
+struct Hashing<R> {
+    inner: R,
+    hasher: Arc<Mutex<Sha256>>,
+}
+
+impl<R: Read> Read for Hashing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher
+            .lock()
+            .expect("Ningún hilo entra en pánico con el candado")
+            .update(&buf[..n]);
+
+        Ok(n)
+    }
+}
@@ -0,0 +1,328 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::{
+    config::Config,
+    database::{DataBase, GeneVariations, IngestError, QueryError},
+    format::FormatError,
+    genes, input,
+    summary::{GeneMeans, Summary},
+    variations::{AmClass, Variation},
+};
+
+/// Id de un transcrito sin su versión: `ENST00000269305` para `ENST00000269305.9`.
+pub(crate) fn unversioned(transcript: &str) -> &str {
+    transcript.split('.').next().unwrap_or(transcript)
+}
+
+/// Errores al abrir la base de datos de isoformas.
+#[derive(Debug)]
+pub enum IsoformsError {
+    /// La base de datos está dañada o la construyó una versión incompatible.
+    Database(FormatError),
+    /// No se pudo leer la tabla de transcritos.
+    Transcripts(IngestError),
+}
+
+impl std::fmt::Display for IsoformsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsoformsError::Database(e) => write!(f, "Base de datos de isoformas: {}", e),
+            IsoformsError::Transcripts(e) => write!(f, "Tabla de transcritos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for IsoformsError {}
+
+/// Relación entre transcritos y genes.
+#[derive(Debug, Default)]
+pub struct TranscriptMap {
+    /// Símbolo del gen de cada transcrito, sin versión.
+    genes: HashMap<String, String>,
+    /// Transcritos, sin versión, de cada gen, por su símbolo en mayúsculas.
+    transcripts: HashMap<String, Vec<String>>,
+}
+
+impl TranscriptMap {
+    /// Lee una tabla separada por tabuladores, comprimida o no, con una columna de
+    /// transcritos y otra de genes, como la que exporta BioMart de Ensembl con
+    /// `Transcript stable ID` y `Gene name`. Las columnas se identifican por su nombre en
+    /// el encabezado: la de transcritos es la primera que contiene `transcript`, y la de
+    /// genes la primera que se llame `Gene name`, `gene_name`, `symbol` o `gene`.
+    pub fn load(path: &Path) -> Result<Self, IngestError> {
+        TranscriptMap::read(input::decompress(File::open(path)?)?)
+    }
+
+    fn read(file: impl BufRead) -> Result<Self, IngestError> {
+        let mut lines = file.lines().enumerate();
+        let header = loop {
+            match lines.next() {
+                Some((_, line)) => {
+                    let line = line?;
+                    if !line.starts_with('#') || line.contains('\t') {
+                        break line.trim_start_matches('#').to_lowercase();
+                    }
+                }
+                None => return Ok(TranscriptMap::default()),
+            }
+        };
+        let names: Vec<&str> = header.split('\t').map(str::trim).collect();
+        let transcript = names
+            .iter()
+            .position(|name| name.contains("transcript"))
+            .ok_or(IngestError::MissingHeaderColumn { name: "transcript" })?;
+        let gene = names
+            .iter()
+            .position(|name| ["gene name", "gene_name", "symbol", "gene"].contains(name))
+            .ok_or(IngestError::MissingHeaderColumn { name: "gene name" })?;
+
+        let mut map = TranscriptMap::default();
+        for (number, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values: Vec<&str> = line.split('\t').collect();
+            let column = |index: usize| {
+                values
+                    .get(index)
+                    .map(|value| value.trim())
+                    .ok_or(IngestError::MissingColumn {
+                        line: number + 1,
+                        column: index + 1,
+                    })
+            };
+            let (transcript, gene) = (column(transcript)?, column(gene)?);
+            if !transcript.is_empty() && !gene.is_empty() {
+                map.insert(transcript, gene);
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Agrega un transcrito del gen `gene`.
+    pub fn insert(&mut self, transcript: &str, gene: &str) {
+        let transcript = unversioned(transcript).to_owned();
+        let transcripts = self.transcripts.entry(gene.to_uppercase()).or_default();
+
+        if let Err(index) = transcripts.binary_search(&transcript) {
+            transcripts.insert(index, transcript.clone());
+        }
+        self.genes.insert(transcript, gene.to_owned());
+    }
+
+    /// Símbolo del gen del transcrito, con o sin versión.
+    pub fn gene(&self, transcript: &str) -> Option<&str> {
+        self.genes.get(unversioned(transcript)).map(String::as_str)
+    }
+
+    /// Transcritos del gen con símbolo `gene`, sin versión y en orden alfabético.
+    pub fn transcripts(&self, gene: &str) -> &[String] {
+        self.transcripts
+            .get(&gene.to_uppercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// Base de datos de patogenicidad de todas las isoformas, construida a partir de
+/// `AlphaMissense_isoforms_aa_substitutions.tsv.gz` e indexada por id de transcrito de
+/// Ensembl, junto con el gen de cada transcrito.
+pub struct Isoforms {
+    database: DataBase,
+    /// Id con versión, como está en la base de datos, de cada transcrito sin versión.
+    versions: HashMap<String, String>,
+    transcripts: TranscriptMap,
+}
+
+impl Isoforms {
+    /// Archivos de entrada que se buscan si no se indica uno, en orden de preferencia.
+    const INPUT: [&'static str; 2] = [
+        "AlphaMissense_isoforms_aa_substitutions.tsv.gz",
+        "AlphaMissense_isoforms_aa_substitutions.tsv",
+    ];
+
+    pub fn new(database: DataBase, transcripts: TranscriptMap) -> Self {
+        let versions = database
+            .ids()
+            .map(|id| (unversioned(id).to_owned(), id.to_owned()))
+            .collect();
+
+        Isoforms {
+            database,
+            versions,
+            transcripts,
+        }
+    }
+
+    /// Abre la base de datos de isoformas indicada en la configuración, si ya se
+    /// construyó con `build-isoforms`, junto con la relación entre transcritos y genes si
+    /// se indica una.
+    pub fn open(config: &Config) -> Result<Option<Self>, IsoformsError> {
+        if !config.isoforms.exists() {
+            return Ok(None);
+        }
+
+        let database = DataBase::load(&config.isoforms).map_err(IsoformsError::Database)?;
+        let transcripts = match &config.transcripts {
+            Some(path) => TranscriptMap::load(path).map_err(IsoformsError::Transcripts)?,
+            None => TranscriptMap::default(),
+        };
+
+        Ok(Some(Isoforms::new(database, transcripts)))
+    }
+
+    /// Regresa `input` o, si no se indica, la primera de las tablas de isoformas en
+    /// [`Isoforms::INPUT`] que exista.
+    pub fn input(input: Option<&Path>) -> Option<&Path> {
+        input::find(input, &Isoforms::INPUT)
+    }
+
+    /// Base de datos de isoformas, indexada por id de transcrito con versión.
+    pub fn database(&self) -> &DataBase {
+        &self.database
+    }
+
+    /// Id del transcrito como está en la base de datos, si está. Se acepta con o sin
+    /// versión.
+    pub fn resolve<'a>(&'a self, transcript: &str) -> Option<&'a str> {
+        self.versions
+            .get(unversioned(transcript))
+            .map(String::as_str)
+    }
+
+    /// Variaciones del transcrito, con o sin versión.
+    pub fn get(&self, transcript: &str) -> Result<Option<Cow<'_, GeneVariations>>, FormatError> {
+        match self.resolve(transcript) {
+            Some(transcript) => self.database.get(transcript),
+            None => Ok(None),
+        }
+    }
+
+    /// Símbolo del gen del transcrito, con o sin versión.
+    pub fn gene(&self, transcript: &str) -> Option<&str> {
+        self.transcripts.gene(transcript)
+    }
+
+    /// Transcritos con predicciones de `gene`, que puede ser el símbolo del gen, el id de
+    /// uniprot de su proteína o uno de sus transcritos. Los ids llevan su versión.
+    pub fn isoforms(&self, gene: &str) -> Vec<&str> {
+        let symbol = match (self.resolve(gene), genes::name(gene)) {
+            (Some(transcript), _) => match self.gene(transcript) {
+                Some(symbol) => symbol,
+                None => return vec![transcript],
+            },
+            (None, Some(symbol)) => symbol,
+            (None, None) => gene,
+        };
+
+        self.transcripts
+            .transcripts(symbol)
+            .iter()
+            .filter_map(|transcript| self.resolve(transcript))
+            .collect()
+    }
+
+    /// Clasificación de `variation` en el transcrito, revisando su aminoácido de
+    /// referencia como [`GeneVariations::query`].
+    pub fn query(&self, transcript: &str, variation: &Variation) -> Result<AmClass, QueryError> {
+        match self.resolve(transcript) {
+            Some(transcript) => self.database.query(transcript, variation),
+            None => Err(QueryError::UnknownProtein {
+                uniprot_id: transcript.to_owned(),
+            }),
+        }
+    }
+
+    /// Resumen de las predicciones del transcrito, con la media publicada si se tiene la
+    /// tabla de medias.
+    pub fn summary(
+        &self,
+        transcript: &str,
+        means: Option<&GeneMeans>,
+    ) -> Result<Summary, QueryError> {
+        match self.resolve(transcript) {
+            Some(transcript) => self.database.summary(transcript, means),
+            None => Err(QueryError::UnknownProtein {
+                uniprot_id: transcript.to_owned(),
+            }),
+        }
+    }
+
+    /// Clasificación de `variation` en cada isoforma de `gene`, como en
+    /// [`Isoforms::isoforms`]. Como las isoformas difieren en su secuencia, la referencia
+    /// puede coincidir solo en algunas.
+    pub fn query_gene(
+        &self,
+        gene: &str,
+        variation: &Variation,
+    ) -> Vec<(&str, Result<AmClass, QueryError>)> {
+        let transcripts = self.isoforms(gene);
+        let classes = self.database.query_batch(
+            transcripts
+                .iter()
+                .map(|transcript| (*transcript, variation)),
+        );
+
+        transcripts.into_iter().zip(classes).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::database::{tests::substitutions_table, BuildOptions};
+
+    #[test]
+    fn variations_are_scored_on_every_isoform() {
+        let transcripts = TranscriptMap::read(
+            "Gene stable ID\tTranscript stable ID\tGene name\n\
+             ENSG00000141510\tENST00000269305\tTP53\n\
+             ENSG00000141510\tENST00000413465\tTP53\n\
+             ENSG00000141510\tENST00000999999\tTP53\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(transcripts.gene("ENST00000269305.9"), Some("TP53"));
+        assert_eq!(transcripts.transcripts("tp53").len(), 3);
+
+        let path = substitutions_table(
+            "isoformas",
+            "transcript_id",
+            &[
+                "ENST00000269305.9 R2H 0.9 pathogenic",
+                "ENST00000269305.9 R2C 0.1 benign",
+                "ENST00000413465.6 K2H 0.4 ambiguous",
+            ],
+        );
+        let database = DataBase::build(&path, &BuildOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let isoforms = Isoforms::new(database, transcripts);
+        assert_eq!(
+            isoforms.isoforms("TP53"),
+            ["ENST00000269305.9", "ENST00000413465.6"]
+        );
+        assert_eq!(
+            isoforms.isoforms("ENST00000413465"),
+            isoforms.isoforms("tp53")
+        );
+
+        let variation = Variation::from_str("R2H").unwrap();
+        let results = isoforms.query_gene("TP53", &variation);
+        assert_eq!(results[0].0, "ENST00000269305.9");
+        assert!(matches!(results[0].1, Ok(AmClass::Pathogenic(_))));
+        assert!(matches!(
+            results[1].1,
+            Err(QueryError::ReferenceMismatch { position: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,78 @@
+//! Buscador de patogenicidad en missense vía AlphaMissense.
+//!
+//! La biblioteca construye y consulta una base de datos con las predicciones de
+//! AlphaMissense para todas las sustituciones de aminoácidos del proteoma humano.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use std::str::FromStr;
+//!
+//! use alphamissense_db::{DataBase, Variation};
+//!
+//! let database = DataBase::load(Path::new("variations.cdv"))?;
+//! let variation = Variation::from_str("R175H").unwrap();
+//! let class = database.get("P04637")?.map(|gene| gene.query(&variation));
+//! # Ok::<(), alphamissense_db::FormatError>(())
+//! ```
+//!
+//! La base de datos se construye con [`DataBase::build`]. El servidor, el motor de
+//! búsqueda y el cliente de uniprot están detrás de las características `server`,
+//! `search` y `uniprot`, para que quien solo use la base de datos no dependa de Rocket,
+//! Tantivy ni reqwest.
+
+/// Módulo donde se definen operaciones refentes a Aminoacidos.
+pub mod aminoacids;
+/// Módulo que define la configuración del programa.
+pub mod config;
+/// Módulo donde se definen las operaciones para construir la Base de Datos
+pub mod database;
+/// Módulo que compara las regiones anotadas de cada proteína con sus predicciones.
+pub mod features;
+/// Módulo que define el formato en disco de la base de datos.
+mod format;
+/// Módulo con el nombre del gen de cada proteína.
+pub mod genes;
+/// Módulo que define el índice de predicciones por coordenada genómica.
+pub mod genomic;
+/// Módulo que arma la matriz de patogenicidades de una proteína.
+pub mod heatmap;
+/// Módulo que lee la tabla de genes de HGNC.
+pub mod hgnc;
+/// Módulo para abrir archivos de entrada, comprimidos o no.
+mod input;
+/// Módulo que define la base de datos de isoformas.
+pub mod isoforms;
+/// Módulo que convierte coordenadas entre ensambles del genoma.
+pub mod liftover;
+/// Módulo que lee volcados locales de UniProtKB y guarda las entradas de cada proteína.
+pub mod proteins;
+/// Módulo que define el motor de búsqueda por texto.
+#[cfg(feature = "search")]
+pub mod search;
+/// Módulo que define el servidor web.
+#[cfg(feature = "server")]
+pub mod server;
+/// Módulo que resume las predicciones de cada proteína.
+pub mod summary;
+/// Módulo que dibuja las predicciones de una proteína como SVG.
+pub mod svg;
+/// Módulo donde se define la operación para extraer el nombre de un gen, dada su id en uniprot.
+#[cfg(feature = "uniprot")]
+pub mod uniprot;
+/// Módulo donde se definen operaciones sobre variaciones.
+pub mod variations;
+/// Módulo para anotar archivos VCF con las predicciones del índice genómico.
+pub mod vcf;
+
+pub use aminoacids::{AminoAcid, AminoAcidMap};
+pub use database::{
+    BuildOptions, DataBase, Encoding, GeneVariations, IngestError, Metadata, QueryError,
+};
+pub use features::FeatureScore;
+pub use format::FormatError;
+pub use genomic::{Annotation, Assembly, Genome, GenomicError, GenomicIndex};
+pub use heatmap::{Heatmap, HeatmapOptions};
+pub use isoforms::{Isoforms, TranscriptMap};
+pub use proteins::ProteinStore;
+pub use summary::{GeneMeans, Summary};
+pub use variations::{AmClass, Cutoffs, ProteinChange, Variation, VariationError};
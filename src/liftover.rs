@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use crate::input;
+
+/// Errores al leer un archivo de cadenas de UCSC.
+#[derive(Debug)]
+pub enum ChainError {
+    /// No se pudo leer el archivo.
+    Io(io::Error),
+    /// La línea no tiene el formato de una cadena ni de un bloque.
+    Malformed { line: usize, text: String },
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainError::Io(e) => write!(f, "Error al leer el archivo de cadenas: {}", e),
+            ChainError::Malformed { line, text } => {
+                write!(
+                    f,
+                    "Línea {} del archivo de cadenas inválida: {:?}",
+                    line, text
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+impl From<io::Error> for ChainError {
+    fn from(e: io::Error) -> Self {
+        ChainError::Io(e)
+    }
+}
+
+/// Región alineada sin huecos entre los dos ensambles. Las coordenadas empiezan en 0.
+#[derive(Debug, Clone)]
+struct Block {
+    start: u64,
+    end: u64,
+    /// Mayor `end` de este bloque y de todos los anteriores, para buscar entre bloques
+    /// que se traslapan.
+    reach: u64,
+    /// Posición del cromosoma de destino en [`Liftover::chromosomes`].
+    chromosome: usize,
+    /// Inicio del bloque en el destino, en la cadena indicada por `reverse`.
+    destination: u64,
+    /// Si el bloque se alinea con la cadena complementaria del destino.
+    reverse: bool,
+    /// Tamaño del cromosoma de destino.
+    size: u64,
+    /// Puntaje de la cadena: si una posición está en varias, se usa la de mayor puntaje.
+    score: u64,
+}
+
+/// Posición convertida al otro ensamble.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lifted {
+    pub chromosome: String,
+    /// Posición, contando desde 1.
+    pub position: u32,
+    /// Si la región está invertida en el otro ensamble, en cuyo caso los alelos deben
+    /// complementarse.
+    pub reverse: bool,
+}
+
+/// Conversión de coordenadas entre ensambles del genoma a partir de un archivo de cadenas
+/// de UCSC, como `hg19ToHg38.over.chain.gz`.
+#[derive(Debug)]
+pub struct Liftover {
+    chromosomes: Vec<String>,
+    /// Bloques por cromosoma de origen, ordenados por inicio.
+    blocks: HashMap<String, Vec<Block>>,
+}
+
+impl Liftover {
+    /// Lee un archivo de cadenas, comprimido con gzip o no.
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        Liftover::read(input::decompress(File::open(path)?)?)
+    }
+
+    fn read(file: impl BufRead) -> Result<Self, ChainError> {
+        let mut chromosomes: Vec<String> = Vec::new();
+        let mut blocks: HashMap<String, Vec<Block>> = HashMap::new();
+
+        // Cadena actual: cromosoma de origen, posición en ambos ensambles y el bloque base.
+        let mut chain: Option<(String, u64, u64, Block)> = None;
+
+        for (number, line) in file.lines().enumerate() {
+            let line = line?;
+            let malformed = || ChainError::Malformed {
+                line: number + 1,
+                text: line.clone(),
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                [] => chain = None,
+                ["chain", score, source, _, "+", start, _, destination, size, strand, qstart, _, ..] =>
+                {
+                    let number = |text: &str| text.parse::<u64>().map_err(|_| malformed());
+                    let chromosome = match chromosomes.iter().position(|c| c == destination) {
+                        Some(index) => index,
+                        None => {
+                            chromosomes.push(destination.to_string());
+                            chromosomes.len() - 1
+                        }
+                    };
+                    let reverse = match *strand {
+                        "+" => false,
+                        "-" => true,
+                        _ => return Err(malformed()),
+                    };
+
+                    chain = Some((
+                        source.to_string(),
+                        number(start)?,
+                        number(qstart)?,
+                        Block {
+                            start: 0,
+                            end: 0,
+                            reach: 0,
+                            chromosome,
+                            destination: 0,
+                            reverse,
+                            size: number(size)?,
+                            score: number(score)?,
+                        },
+                    ));
+                }
+                [size, gaps @ ..] if gaps.len() == 2 || gaps.is_empty() => {
+                    let (source, start, destination, base) =
+                        chain.as_mut().ok_or_else(malformed)?;
+                    let number = |text: &str| text.parse::<u64>().map_err(|_| malformed());
+                    let size = number(size)?;
+
+                    blocks.entry(source.clone()).or_default().push(Block {
+                        start: *start,
+                        end: *start + size,
+                        destination: *destination,
+                        ..base.clone()
+                    });
+
+                    *start += size;
+                    *destination += size;
+                    if let [source_gap, destination_gap] = gaps {
+                        *start += number(source_gap)?;
+                        *destination += number(destination_gap)?;
+                    }
+                }
+                _ => return Err(malformed()),
+            }
+        }
+
+        for blocks in blocks.values_mut() {
+            blocks.sort_by_key(|block| block.start);
+            let mut reach = 0;
+            for block in blocks {
+                reach = reach.max(block.end);
+                block.reach = reach;
+            }
+        }
+
+        Ok(Liftover {
+            chromosomes,
+            blocks,
+        })
+    }
+
+    /// Convierte `position` (desde 1) de `chromosome`. Regresa `None` si la posición no
+    /// está en ninguna región alineada.
+    pub fn lift(&self, chromosome: &str, position: u32) -> Option<Lifted> {
+        let blocks = self.blocks.get(chromosome)?;
+        let position = (position as u64).checked_sub(1)?;
+
+        let end = blocks.partition_point(|block| block.start <= position);
+        let block = blocks[..end]
+            .iter()
+            .rev()
+            .take_while(|block| block.reach > position)
+            .filter(|block| position < block.end)
+            .max_by_key(|block| block.score)?;
+
+        let offset = block.destination + position - block.start;
+        let lifted = match block.reverse {
+            true => block.size - 1 - offset,
+            false => offset,
+        };
+
+        Some(Lifted {
+            chromosome: self.chromosomes[block.chromosome].clone(),
+            position: u32::try_from(lifted + 1).ok()?,
+            reverse: block.reverse,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: &str = "\
+chain 1000 chr1 1000 + 100 300 chr1 1200 + 200 400 1
+50 10 10
+140
+
+chain 500 chr2 1000 + 0 100 chr3 1000 - 0 100 2
+100
+";
+
+    #[test]
+    fn lifts_positions_through_blocks_and_gaps() {
+        let liftover = Liftover::read(CHAIN.as_bytes()).unwrap();
+        let lifted = |chromosome, position| {
+            liftover
+                .lift(chromosome, position)
+                .map(|l| (l.chromosome, l.position, l.reverse))
+        };
+
+        assert_eq!(lifted("chr1", 101), Some(("chr1".to_owned(), 201, false)));
+        assert_eq!(lifted("chr1", 150), Some(("chr1".to_owned(), 250, false)));
+        assert_eq!(lifted("chr1", 155), None);
+        assert_eq!(lifted("chr1", 161), Some(("chr1".to_owned(), 261, false)));
+        assert_eq!(lifted("chr1", 301), None);
+        assert_eq!(lifted("chr2", 1), Some(("chr3".to_owned(), 1000, true)));
+        assert_eq!(lifted("chr2", 100), Some(("chr3".to_owned(), 901, true)));
+        assert_eq!(lifted("chrX", 1), None);
+
+        assert!(matches!(
+            Liftover::read("50 10 10\n".as_bytes()),
+            Err(ChainError::Malformed { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,844 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+
+use bincode::Options;
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    config::Config,
+    database::Metadata,
+    format::{self, FormatError},
+    genes::GeneInfo,
+    input,
+};
+
+/// Región o sitio anotado en una proteína, como un dominio o un sitio activo.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Feature {
+    /// Tipo, con los nombres del XML de UniProt: `domain`, `active site`,
+    /// `binding site`, `disulfide bond`, `transmembrane region`...
+    pub kind: String,
+    /// Primera posición, contando desde 1.
+    pub start: u16,
+    /// Última posición, incluida. En los puentes disulfuro, la segunda cisteína.
+    pub end: u16,
+    pub description: Option<String>,
+}
+
+impl Feature {
+    /// Posiciones que abarca la región. Un puente disulfuro solo abarca sus dos cisteínas.
+    pub fn positions(&self) -> Vec<u16> {
+        match self.kind.as_str() {
+            DISULFIDE_BOND if self.start != self.end => vec![self.start, self.end],
+            _ => (self.start..=self.end).collect(),
+        }
+    }
+
+    /// Si la región abarca `position`, contando desde 1.
+    pub fn covers(&self, position: u16) -> bool {
+        match self.kind.as_str() {
+            DISULFIDE_BOND => position == self.start || position == self.end,
+            _ => (self.start..=self.end).contains(&position),
+        }
+    }
+}
+
+/// Lo que se toma de cada entrada de UniProtKB.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    pub accession: String,
+    pub secondary_accessions: Vec<String>,
+    /// Nombres del gen, el principal primero.
+    pub gene_names: Vec<String>,
+    /// Nombres de la proteína, el recomendado primero.
+    pub protein_names: Vec<String>,
+    pub sequence: String,
+    pub features: Vec<Feature>,
+}
+
+impl Entry {
+    /// Lo que se sabe del gen de la proteína `uniprot_id`, para el buscador.
+    pub fn gene_info(&self, uniprot_id: &str) -> GeneInfo {
+        GeneInfo {
+            uniprot_id: uniprot_id.to_owned(),
+            symbol: self.gene_names.first().cloned().unwrap_or_default(),
+            protein_names: self.protein_names.clone(),
+            aliases: self.gene_names.iter().skip(1).cloned().collect(),
+            accessions: std::iter::once(&self.accession)
+                .chain(&self.secondary_accessions)
+                .filter(|accession| *accession != uniprot_id)
+                .cloned()
+                .collect(),
+            ..GeneInfo::default()
+        }
+    }
+}
+
+/// Errores al leer un volcado de UniProtKB.
+#[derive(Debug)]
+pub enum DumpError {
+    Io(io::Error),
+    /// El XML no está bien formado.
+    Xml(String),
+    Json(serde_json::Error),
+    /// Una línea del archivo de texto de UniProtKB no tiene el formato esperado.
+    Malformed {
+        line: usize,
+        text: String,
+    },
+    /// El archivo no parece ser de texto, XML ni JSON de UniProtKB.
+    UnknownFormat,
+    Output(FormatError),
+}
+
+impl std::fmt::Display for DumpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpError::Io(e) => write!(f, "Error al leer el volcado de UniProtKB: {}", e),
+            DumpError::Xml(e) => write!(f, "XML de UniProtKB inválido: {}", e),
+            DumpError::Json(e) => write!(f, "JSON de UniProtKB inválido: {}", e),
+            DumpError::Malformed { line, text } => {
+                write!(
+                    f,
+                    "Línea {} del volcado de UniProtKB inválida: {:?}",
+                    line, text
+                )
+            }
+            DumpError::UnknownFormat => write!(
+                f,
+                "El volcado no es un archivo de texto (.dat), XML ni JSON de UniProtKB"
+            ),
+            DumpError::Output(e) => write!(f, "No se pudo escribir las proteínas: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
+
+impl From<io::Error> for DumpError {
+    fn from(e: io::Error) -> Self {
+        DumpError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DumpError {
+    fn from(e: serde_json::Error) -> Self {
+        DumpError::Json(e)
+    }
+}
+
+/// Formato de un volcado de UniProtKB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Archivo de texto, como `uniprot_sprot_human.dat.gz`.
+    Flat,
+    Xml,
+    /// Un objeto con `results`, como los que regresa la API, una lista de entradas o una
+    /// entrada por línea.
+    Json,
+}
+
+impl DumpFormat {
+    /// Reconoce el formato por los primeros bytes del archivo, ya descomprimido.
+    fn detect(file: &mut impl BufRead) -> Result<Self, DumpError> {
+        let start = file.fill_buf()?;
+        let first = start.iter().find(|byte| !byte.is_ascii_whitespace());
+
+        match first {
+            Some(b'<') => Ok(DumpFormat::Xml),
+            Some(b'{') | Some(b'[') => Ok(DumpFormat::Json),
+            _ if start.starts_with(b"ID   ") => Ok(DumpFormat::Flat),
+            _ => Err(DumpError::UnknownFormat),
+        }
+    }
+}
+
+/// Quita las evidencias, como `{ECO:0000269|PubMed:123}`, de un valor del archivo de texto.
+fn without_evidence(value: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    for c in value.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ if depth == 0 => result.push(c),
+            _ => {}
+        }
+    }
+
+    result.trim().to_owned()
+}
+
+// Nombres en `Feature::kind` de los tipos de región que se comparan con las predicciones.
+pub(crate) const DOMAIN: &str = "domain";
+pub(crate) const ACTIVE_SITE: &str = "active site";
+pub(crate) const BINDING_SITE: &str = "binding site";
+pub(crate) const DISULFIDE_BOND: &str = "disulfide bond";
+pub(crate) const TRANSMEMBRANE: &str = "transmembrane region";
+
+/// Tipos de región del archivo de texto y de JSON, con su nombre en XML.
+const FEATURE_KINDS: [(&str, &str, &str); 14] = [
+    ("DOMAIN", "Domain", DOMAIN),
+    ("ACT_SITE", "Active site", ACTIVE_SITE),
+    ("BINDING", "Binding site", BINDING_SITE),
+    ("SITE", "Site", "site"),
+    ("DISULFID", "Disulfide bond", DISULFIDE_BOND),
+    ("TRANSMEM", "Transmembrane", TRANSMEMBRANE),
+    ("INTRAMEM", "Intramembrane", "intramembrane region"),
+    ("TOPO_DOM", "Topological domain", "topological domain"),
+    ("REGION", "Region", "region of interest"),
+    ("MOTIF", "Motif", "short sequence motif"),
+    ("ZN_FING", "Zinc finger", "zinc finger region"),
+    ("DNA_BIND", "DNA binding", "DNA-binding region"),
+    ("SIGNAL", "Signal", "signal peptide"),
+    ("MOD_RES", "Modified residue", "modified residue"),
+];
+
+/// Nombre en XML de un tipo de región del archivo de texto o de JSON.
+fn feature_kind(kind: &str) -> String {
+    FEATURE_KINDS
+        .iter()
+        .find(|(flat, json, _)| *flat == kind || *json == kind)
+        .map(|(_, _, xml)| xml.to_string())
+        .unwrap_or_else(|| kind.to_lowercase().replace('_', " "))
+}
+
+/// Posición de una ubicación como `<1`, `>300` o `150`. Las desconocidas (`?`) no tienen.
+fn location(text: &str) -> Option<u16> {
+    text.trim().trim_start_matches(['<', '>']).parse().ok()
+}
+
+/// Lee un volcado de UniProtKB, comprimido o no, en cualquiera de sus formatos, y llama a
+/// `f` con cada entrada.
+pub fn read(file: impl Read + Send + 'static, f: impl FnMut(Entry)) -> Result<(), DumpError> {
+    let mut file = input::decompress(file)?;
+
+    match DumpFormat::detect(&mut file)? {
+        DumpFormat::Flat => read_flat(file, f),
+        DumpFormat::Xml => read_xml(file, f),
+        DumpFormat::Json => read_json(file, f),
+    }
+}
+
+fn read_flat(file: impl BufRead, mut f: impl FnMut(Entry)) -> Result<(), DumpError> {
+    let mut entry = Entry::default();
+    let mut accessions = Vec::new();
+    // Calificador de la última región, que puede seguir en las líneas siguientes.
+    let mut qualifier = String::new();
+
+    let finish_qualifier = |entry: &mut Entry, qualifier: &mut String| {
+        let text = std::mem::take(qualifier);
+        if let Some(note) = text.strip_prefix("/note=") {
+            if let Some(feature) = entry.features.last_mut() {
+                feature.description = Some(without_evidence(note.trim_matches('"')));
+            }
+        }
+    };
+
+    for (number, line) in file.lines().enumerate() {
+        let line = line?;
+        let (code, value) = (line.get(..2).unwrap_or(&line), line.get(5..).unwrap_or(""));
+        let malformed = || DumpError::Malformed {
+            line: number + 1,
+            text: line.clone(),
+        };
+
+        if code != "FT" {
+            finish_qualifier(&mut entry, &mut qualifier);
+        }
+        match code {
+            "AC" => accessions.extend(
+                value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|accession| !accession.is_empty())
+                    .map(str::to_owned),
+            ),
+            "DE" => {
+                let value = value.trim();
+                let name = ["RecName: Full=", "AltName: Full=", "SubName: Full="]
+                    .into_iter()
+                    .find_map(|prefix| value.strip_prefix(prefix));
+                if let Some(name) = name {
+                    entry
+                        .protein_names
+                        .push(without_evidence(name.trim_end_matches(';')));
+                }
+            }
+            "GN" => {
+                for part in value.split(';') {
+                    let part = part.trim();
+                    if let Some(name) = part.strip_prefix("Name=") {
+                        entry.gene_names.insert(0, without_evidence(name));
+                    } else if let Some(synonyms) = part.strip_prefix("Synonyms=") {
+                        entry
+                            .gene_names
+                            .extend(synonyms.split(',').map(without_evidence));
+                    }
+                }
+            }
+            "FT" => {
+                let kind = value.get(..16).unwrap_or(value).trim();
+                let rest = value.get(16..).unwrap_or("").trim();
+                if !kind.is_empty() {
+                    finish_qualifier(&mut entry, &mut qualifier);
+                    // Las ubicaciones en otras entradas, como `P12345-2:1..10`, se omiten.
+                    if rest.contains(':') {
+                        continue;
+                    }
+                    let (start, end) = rest.split_once("..").unwrap_or((rest, rest));
+                    if let (Some(start), Some(end)) = (location(start), location(end)) {
+                        entry.features.push(Feature {
+                            kind: feature_kind(kind),
+                            start,
+                            end,
+                            description: None,
+                        });
+                    }
+                } else if rest.starts_with('/') {
+                    finish_qualifier(&mut entry, &mut qualifier);
+                    qualifier = rest.to_owned();
+                } else if !qualifier.is_empty() {
+                    qualifier.push(' ');
+                    qualifier.push_str(rest);
+                }
+            }
+            "  " => entry
+                .sequence
+                .extend(line.chars().filter(|c| c.is_ascii_alphabetic())),
+            "//" => {
+                let mut accessions = std::mem::take(&mut accessions).into_iter();
+                entry.accession = accessions.next().ok_or_else(malformed)?;
+                entry.secondary_accessions = accessions.collect();
+                f(std::mem::take(&mut entry));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn read_xml(file: impl BufRead, mut f: impl FnMut(Entry)) -> Result<(), DumpError> {
+    let mut entry = Entry::default();
+    // Nombres de los elementos abiertos, a partir de `entry`.
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut feature: Option<Feature> = None;
+
+    for event in EventReader::new(file) {
+        match event.map_err(|e| DumpError::Xml(e.to_string()))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|attribute| attribute.name.local_name == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                let name = name.local_name;
+                if name == "entry" {
+                    path.clear();
+                    entry = Entry::default();
+                }
+                match (path.last().map(String::as_str), name.as_str()) {
+                    (Some("entry"), "feature") => {
+                        feature = Some(Feature {
+                            kind: attribute("type").unwrap_or_default(),
+                            start: 0,
+                            end: 0,
+                            description: attribute("description"),
+                        })
+                    }
+                    (Some("location"), "begin" | "end" | "position") => {
+                        let position = attribute("position").and_then(|p| location(&p));
+                        if let (Some(feature), Some(position)) = (feature.as_mut(), position) {
+                            if name != "end" {
+                                feature.start = position;
+                            }
+                            if name != "begin" {
+                                feature.end = position;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            XmlEvent::Characters(characters) | XmlEvent::CData(characters) => {
+                text.push_str(&characters)
+            }
+            XmlEvent::EndElement { .. } => {
+                let name = path.pop().unwrap_or_default();
+                let parents: Vec<&str> = path.iter().rev().take(2).map(String::as_str).collect();
+                let value = text.trim().to_owned();
+                match (name.as_str(), parents.as_slice()) {
+                    ("accession", ["entry", ..]) if entry.accession.is_empty() => {
+                        entry.accession = value
+                    }
+                    ("accession", ["entry", ..]) => entry.secondary_accessions.push(value),
+                    ("name", ["gene", "entry"]) => entry.gene_names.push(value),
+                    ("fullName", [_, "protein"]) => entry.protein_names.push(value),
+                    ("sequence", ["entry", ..]) => {
+                        entry.sequence = value.split_whitespace().collect()
+                    }
+                    ("feature", ["entry", ..]) => {
+                        if let Some(feature) = feature.take() {
+                            if feature.start > 0 && feature.end > 0 {
+                                entry.features.push(feature);
+                            }
+                        }
+                    }
+                    ("entry", _) => f(std::mem::take(&mut entry)),
+                    _ => {}
+                }
+                text.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn json_text(value: &Value, path: &[&str]) -> Option<String> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+}
+
+fn json_list<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+impl Entry {
+    /// Lee una entrada en el JSON de la API de UniProt, como
+    /// `https://rest.uniprot.org/uniprotkb/P04637.json`. Regresa `None` si no tiene id.
+    pub fn from_json(value: &Value) -> Option<Entry> {
+        let description = value.get("proteinDescription");
+        let mut protein_names: Vec<String> = description
+            .and_then(|d| json_text(d, &["recommendedName", "fullName", "value"]))
+            .into_iter()
+            .collect();
+        for key in ["alternativeNames", "submissionNames"] {
+            protein_names.extend(
+                description
+                    .into_iter()
+                    .flat_map(|d| json_list(d, key))
+                    .filter_map(|name| json_text(name, &["fullName", "value"])),
+            );
+        }
+
+        let mut gene_names = Vec::new();
+        for gene in json_list(value, "genes") {
+            gene_names.extend(json_text(gene, &["geneName", "value"]));
+            gene_names.extend(json_list(gene, "synonyms").filter_map(|s| json_text(s, &["value"])));
+        }
+
+        let features = json_list(value, "features")
+            .filter_map(|feature| {
+                let position = |key| {
+                    feature
+                        .get("location")?
+                        .get(key)?
+                        .get("value")?
+                        .as_u64()
+                        .map(|position| position as u16)
+                };
+
+                Some(Feature {
+                    kind: feature_kind(feature.get("type")?.as_str()?),
+                    start: position("start")?,
+                    end: position("end")?,
+                    description: json_text(feature, &["description"]).filter(|d| !d.is_empty()),
+                })
+            })
+            .collect();
+
+        Some(Entry {
+            accession: json_text(value, &["primaryAccession"])?,
+            secondary_accessions: json_list(value, "secondaryAccessions")
+                .filter_map(|accession| accession.as_str().map(str::to_owned))
+                .collect(),
+            gene_names,
+            protein_names,
+            sequence: json_text(value, &["sequence", "value"]).unwrap_or_default(),
+            features,
+        })
+    }
+}
+
+/// Lee entradas en JSON: un objeto con `results`, como los que regresa la API, una lista de
+/// entradas o una entrada por línea. La lista de `results` se lee entrada por entrada, sin
+/// cargar el volcado completo en memoria.
+fn read_json(file: impl BufRead, mut f: impl FnMut(Entry)) -> Result<(), DumpError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(file);
+    while deserializer.end().is_err() {
+        JsonEntries(&mut f).deserialize(&mut deserializer)?;
+    }
+
+    Ok(())
+}
+
+/// Pasa a la función cada entrada de un valor de JSON de [`read_json`] conforme se lee.
+struct JsonEntries<'a, F>(&'a mut F);
+
+impl<'de, F: FnMut(Entry)> DeserializeSeed<'de> for JsonEntries<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, F: FnMut(Entry)> Visitor<'de> for JsonEntries<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .write_str("una entrada de UniProtKB, una lista de entradas o un objeto con `results`")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<Value>()? {
+            if let Some(entry) = Entry::from_json(&value) {
+                (self.0)(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        // Un objeto sin `results` es una entrada, que se guarda completa para leerla.
+        let mut object = serde_json::Map::new();
+        let mut results = false;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "results" => {
+                    map.next_value_seed(JsonEntries(&mut *self.0))?;
+                    results = true;
+                }
+                _ => {
+                    object.insert(key, map.next_value()?);
+                }
+            }
+        }
+        if !results {
+            if let Some(entry) = Entry::from_json(&Value::Object(object)) {
+                (self.0)(entry);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Metadatos que se guardan en la tabla de bloques del archivo.
+#[derive(Serialize, Deserialize)]
+struct ProteinLayout {
+    meta: Metadata,
+}
+
+/// Resultado de [`ProteinStore::build`].
+#[derive(Debug, Default)]
+pub struct BuildSummary {
+    /// Entradas guardadas.
+    pub entries: usize,
+    /// Proteínas pedidas que no están en el volcado, en orden alfabético.
+    pub missing: Vec<String>,
+}
+
+/// Entradas de UniProtKB guardadas junto a la base de datos, por id de uniprot.
+pub struct ProteinStore {
+    mapped: format::Mapped<ProteinLayout>,
+}
+
+impl ProteinStore {
+    /// Lee el volcado de UniProtKB en `input` y guarda en `output` las entradas de las
+    /// proteínas en `wanted`, por el id con el que se piden, que puede ser el principal de
+    /// la entrada o uno secundario. Si `wanted` es `None`, guarda todas.
+    pub fn build(
+        input: &Path,
+        output: &Path,
+        wanted: Option<HashSet<String>>,
+    ) -> Result<BuildSummary, DumpError> {
+        let (file, digest) = input::open_with_digest(input)?;
+        let temporary = output.with_extension("tmp");
+        let mut written: HashSet<String> = HashSet::new();
+        // Si algo falla, no se deja el archivo temporal a medias.
+        let result = (|| -> Result<(), DumpError> {
+            let mut writer = format::Writer::create(&temporary)?;
+            let mut error = None;
+
+            read(file, |entry| {
+                let keys: Vec<String> = match &wanted {
+                    Some(wanted) => std::iter::once(&entry.accession)
+                        .chain(&entry.secondary_accessions)
+                        .filter(|accession| wanted.contains(*accession))
+                        .cloned()
+                        .collect(),
+                    None => vec![entry.accession.clone()],
+                };
+                for key in keys {
+                    // La entrada con el id principal tiene prioridad sobre las que lo tienen
+                    // como secundario, que pueden aparecer antes.
+                    if error.is_some() || !written.insert(key.clone()) && key != entry.accession {
+                        continue;
+                    }
+                    let block = format::options()
+                        .serialize(&entry)
+                        .map_err(|e| DumpError::Output(e.into()));
+                    if let Err(e) = block.and_then(|block| Ok(writer.add(key, &block)?)) {
+                        error = Some(e);
+                    }
+                }
+            })?;
+            if let Some(e) = error {
+                return Err(e);
+            }
+
+            let source = input
+                .file_name()
+                .unwrap_or(input.as_os_str())
+                .to_string_lossy();
+            writer
+                .finish(&ProteinLayout {
+                    meta: Metadata::from_header(&source, Vec::new(), digest.hex()),
+                })
+                .map_err(DumpError::Output)?;
+            Ok(std::fs::rename(&temporary, output)?)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+        result?;
+
+        let mut missing: Vec<String> = wanted
+            .into_iter()
+            .flatten()
+            .filter(|accession| !written.contains(accession))
+            .collect();
+        missing.sort_unstable();
+
+        Ok(BuildSummary {
+            entries: written.len(),
+            missing,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        let file = File::open(path)?;
+
+        Ok(ProteinStore {
+            mapped: format::Mapped::new(&file, format::VERSION)?,
+        })
+    }
+
+    /// Abre el archivo `proteins` de la configuración, si ya se construyó con
+    /// `build-proteins`.
+    pub fn open(config: &Config) -> Result<Option<Self>, FormatError> {
+        if !config.proteins.exists() {
+            return Ok(None);
+        }
+
+        ProteinStore::load(&config.proteins).map(Some)
+    }
+
+    /// Datos del volcado con el que se construyó.
+    pub fn meta(&self) -> &Metadata {
+        &self.mapped.meta().meta
+    }
+
+    /// Ids de uniprot de las proteínas guardadas.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.mapped.keys()
+    }
+
+    /// Entrada de la proteína `uniprot_id`, si está.
+    pub fn get(&self, uniprot_id: &str) -> Result<Option<Entry>, FormatError> {
+        match self.mapped.block(uniprot_id)? {
+            Some(block) => Ok(Some(format::options().deserialize(block)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lo que se sabe del gen de cada proteína guardada, para el buscador.
+    pub fn gene_info(&self) -> Result<Vec<GeneInfo>, FormatError> {
+        self.ids()
+            .filter_map(|id| {
+                self.get(id)
+                    .transpose()
+                    .map(|entry| Ok(entry?.gene_info(id)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const FLAT: &str = "\
+ID   P53_HUMAN               Reviewed;         393 AA.
+AC   P04637; Q15086;
+AC   Q15087;
+DE   RecName: Full=Cellular tumor antigen p53 {ECO:0000305};
+DE   AltName: Full=Tumor suppressor p53;
+GN   Name=TP53 {ECO:0000312|HGNC:HGNC:11998}; Synonyms=P53;
+FT   DOMAIN          100..200
+FT                   /note=\"DNA
+FT                   binding\"
+FT   DISULFID        176..238
+FT   ACT_SITE        <5
+FT   VARIANT         P12345-2:10..20
+SQ   SEQUENCE   12 AA;  1000 MW;  0 CRC64;
+     MEEPQSDPSV EP
+//
+";
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<uniprot xmlns="http://uniprot.org/uniprot">
+<entry dataset="Swiss-Prot">
+  <accession>P04637</accession>
+  <accession>Q15086</accession>
+  <accession>Q15087</accession>
+  <name>P53_HUMAN</name>
+  <protein>
+    <recommendedName><fullName evidence="1">Cellular tumor antigen p53</fullName></recommendedName>
+    <alternativeName><fullName>Tumor suppressor p53</fullName></alternativeName>
+  </protein>
+  <gene><name type="primary">TP53</name><name type="synonym">P53</name></gene>
+  <comment type="alternative products"><isoform><sequence type="displayed"/></isoform></comment>
+  <feature type="domain" description="DNA binding">
+    <location><begin position="100"/><end position="200"/></location>
+  </feature>
+  <feature type="disulfide bond"><location><begin position="176"/><end position="238"/></location></feature>
+  <feature type="active site"><location><position position="5"/></location></feature>
+  <sequence length="12">MEEPQSDPSVEP</sequence>
+</entry>
+</uniprot>
+"#;
+
+    const JSON: &str = r#"{"results": [{
+        "primaryAccession": "P04637",
+        "secondaryAccessions": ["Q15086", "Q15087"],
+        "proteinDescription": {
+            "recommendedName": {"fullName": {"value": "Cellular tumor antigen p53"}},
+            "alternativeNames": [{"fullName": {"value": "Tumor suppressor p53"}}]
+        },
+        "genes": [{"geneName": {"value": "TP53"}, "synonyms": [{"value": "P53"}]}],
+        "features": [
+            {"type": "Domain", "location": {"start": {"value": 100}, "end": {"value": 200}},
+             "description": "DNA binding"},
+            {"type": "Disulfide bond", "location": {"start": {"value": 176}, "end": {"value": 238}},
+             "description": ""},
+            {"type": "Active site", "location": {"start": {"value": 5}, "end": {"value": 5}}}
+        ],
+        "sequence": {"value": "MEEPQSDPSVEP"}
+    }]}"#;
+
+    #[test]
+    fn the_three_formats_agree() {
+        let parse = |dump: &str| {
+            let mut entries = Vec::new();
+            read(io::Cursor::new(dump.to_owned()), |entry| {
+                entries.push(entry)
+            })
+            .unwrap();
+            entries
+        };
+        let flat = parse(FLAT);
+
+        assert_eq!(flat.len(), 1);
+        let entry = &flat[0];
+        assert_eq!(entry.accession, "P04637");
+        assert_eq!(entry.secondary_accessions, ["Q15086", "Q15087"]);
+        assert_eq!(entry.gene_names, ["TP53", "P53"]);
+        assert_eq!(
+            entry.protein_names,
+            ["Cellular tumor antigen p53", "Tumor suppressor p53"]
+        );
+        assert_eq!(entry.sequence, "MEEPQSDPSVEP");
+        assert_eq!(
+            entry.features[0],
+            Feature {
+                kind: "domain".into(),
+                start: 100,
+                end: 200,
+                description: Some("DNA binding".into()),
+            }
+        );
+        assert_eq!(entry.features[1].kind, "disulfide bond");
+        assert_eq!((entry.features[2].start, entry.features[2].end), (5, 5));
+        assert_eq!(entry.features.len(), 3);
+
+        assert_eq!(parse(XML), flat);
+        assert_eq!(parse(JSON), flat);
+
+        let object = JSON
+            .trim_start_matches(r#"{"results": ["#)
+            .trim_end_matches("]}");
+        let object: String = object.lines().map(str::trim).collect();
+        assert_eq!(
+            parse(&format!("{}\n{}\n", object, object)),
+            [&flat[..], &flat[..]].concat()
+        );
+        assert_eq!(parse(&format!("[{}]", object)), flat);
+    }
+
+    #[test]
+    fn stores_the_wanted_accessions() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("uniprot-{}.dat", std::process::id()));
+        let output = dir.join(format!("proteins-{}.cdv", std::process::id()));
+        std::fs::write(&input, FLAT).unwrap();
+
+        let wanted = ["Q15086", "P99999"]
+            .map(str::to_owned)
+            .into_iter()
+            .collect();
+        let summary = ProteinStore::build(&input, &output, Some(wanted)).unwrap();
+        assert_eq!(summary.entries, 1);
+        assert_eq!(summary.missing, ["P99999"]);
+
+        let store = ProteinStore::load(&output).unwrap();
+        let entry = store.get("Q15086").unwrap().unwrap();
+        assert_eq!(entry.accession, "P04637");
+        assert_eq!(store.get("P04637").unwrap(), None);
+
+        let info = store.gene_info().unwrap();
+        assert_eq!(info[0].uniprot_id, "Q15086");
+        assert_eq!(info[0].symbol, "TP53");
+        assert_eq!(info[0].accessions, ["P04637", "Q15087"]);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+    #[test]
+    fn failed_builds_leave_no_temporary_file() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("uniprot-{}.json", std::process::id()));
+        let output = dir.join(format!("proteins-{}-b.cdv", std::process::id()));
+        std::fs::write(&input, &JSON[..JSON.len() / 2]).unwrap();
+
+        assert!(ProteinStore::build(&input, &output, None).is_err());
+        assert!(!output.with_extension("tmp").exists());
+        assert!(!output.exists());
+
+        std::fs::remove_file(input).unwrap();
+    }
+}
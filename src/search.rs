@@ -1,107 +1,330 @@
-use std::{fs, collections::HashMap};
-
-use tantivy::{collector::TopDocs, directory, query::QueryParser, schema::*, Index, ReloadPolicy};
-
-/// Inicializa el índice de búsqueda. Si no existe, lo crea.
-pub fn index() -> tantivy::Result<Index> {
-    match fs::create_dir("index") {
-        Ok(_) => {
-            let dir = directory::MmapDirectory::open("index")?;
-            let mut schema_builder = Schema::builder();
-
-            schema_builder.add_text_field("Nombre_Gen", TEXT | STORED);
-            schema_builder.add_text_field("Uniprot_ID", TEXT | STORED);
-            let schema = schema_builder.build();
-
-            let index = Index::create(dir, schema.clone(), Default::default())?;
-            let mut index_writer = index.writer(50_000_000)?;
-
-            let gene = schema.get_field("Nombre_Gen").unwrap();
-            let uniprot_id = schema.get_field("Uniprot_ID").unwrap();
-
-            let names: HashMap<String, String> = serde_json::from_str(
-                include_str!("genes/names.json")
-            ).map_err(|_| {
-                tantivy::TantivyError::InternalError(
-                    "Failed to initialize the id -> name map.".to_owned()
-                )
-            })?;
-
-            for (n, (id, name)) in names.into_iter().enumerate() {
-                if n % 205 == 0 {
-                    println!("{}%", (100*n)/20516);
-                }
-                index_writer.add_document(doc!(
-                    gene => name,
-                    uniprot_id => id
-                ))?;
-            }
-            println!("100%");
+use std::{fs, path::Path};
+
+use serde::Serialize;
+use tantivy::{
+    collector::TopDocs,
+    directory,
+    query::{Query, QueryParser, TermQuery},
+    schema::*,
+    DocAddress, Index, ReloadPolicy, Term,
+};
+
+use crate::genes::GeneInfo;
 
-            println!("Commiting...");
-            index_writer.commit()?;
-            println!("Commited!");
+/// Campo del índice que hizo que un gen apareciera en la búsqueda.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Matched {
+    Symbol,
+    Accession,
+    Alias,
+    PreviousSymbol,
+    ProteinName,
+}
+
+impl Matched {
+    /// Campos en orden de prioridad: si un gen coincide en varios, se reporta el primero.
+    const ALL: [Matched; 5] = [
+        Matched::Symbol,
+        Matched::Accession,
+        Matched::Alias,
+        Matched::PreviousSymbol,
+        Matched::ProteinName,
+    ];
+
+    /// Campo de texto, guardado en el índice.
+    fn text(&self) -> &'static str {
+        match self {
+            Matched::Symbol => "Nombre_Gen",
+            Matched::Accession => "Uniprot_ID",
+            Matched::Alias => "Alias",
+            Matched::PreviousSymbol => "Simbolo_Previo",
+            Matched::ProteinName => "Nombre_Proteina",
+        }
+    }
 
-            Ok(index)
+    /// Campo con los valores completos en minúsculas, para las coincidencias exactas. Los
+    /// nombres de proteínas no se buscan exactos.
+    fn exact(&self) -> Option<&'static str> {
+        match self {
+            Matched::Symbol => Some("Nombre_Gen_Exacto"),
+            Matched::Accession => Some("Uniprot_ID_Exacto"),
+            Matched::Alias => Some("Alias_Exacto"),
+            Matched::PreviousSymbol => Some("Simbolo_Previo_Exacto"),
+            Matched::ProteinName => None,
         }
-        Err(_) => {
-            let dir = directory::MmapDirectory::open("index")?;
-            let index = Index::open(dir)?;
+    }
+}
 
-            Ok(index)
+impl std::fmt::Display for Matched {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Matched::Symbol => write!(f, "símbolo"),
+            Matched::Accession => write!(f, "id de uniprot"),
+            Matched::Alias => write!(f, "alias"),
+            Matched::PreviousSymbol => write!(f, "símbolo previo"),
+            Matched::ProteinName => write!(f, "nombre de la proteína"),
         }
     }
 }
 
+/// Gen encontrado por [`search`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub uniprot_id: String,
+    pub gene: String,
+    pub matched: Matched,
+    /// Si el texto es exactamente el valor del campo, sin distinguir mayúsculas.
+    pub exact: bool,
+}
+
+/// Número máximo de resultados.
+const LIMIT: usize = 20;
+
+fn schema() -> Schema {
+    let mut schema_builder = Schema::builder();
+    for field in Matched::ALL {
+        schema_builder.add_text_field(field.text(), TEXT | STORED);
+        if let Some(exact) = field.exact() {
+            schema_builder.add_text_field(exact, STRING);
+        }
+    }
 
-/// Regresa los 10 genes que mejor coinciden con la búsqueda.
-pub fn search(index: &Index, n: &str) -> tantivy::Result<Vec<(String, String)>> {
-    let gene = index.schema().get_field("Nombre_Gen")?;
-    let uniprot_id = index.schema().get_field("Uniprot_ID")?;
+    schema_builder.build()
+}
+
+/// Huella de los genes de un índice: cuántos son y el CRC32 de su contenido. Se guarda en
+/// el índice al construirlo.
+fn fingerprint(genes: &[GeneInfo]) -> String {
+    let mut hasher = crc32fast::Hasher::new();
+    for gene in genes {
+        hasher.update(&serde_json::to_vec(gene).expect("Un GeneInfo siempre se puede escribir"));
+    }
+
+    format!("{} genes, {:08x}", genes.len(), hasher.finalize())
+}
+
+/// Inicializa el índice de búsqueda en el directorio `path` con los genes de `genes`. Si
+/// no existe, si fue creado con campos distintos a los actuales o si los genes cambiaron
+/// desde que se creó, lo crea.
+pub fn index(path: &Path, genes: &[GeneInfo]) -> tantivy::Result<Index> {
+    let fingerprint = fingerprint(genes);
+    if path.exists() {
+        let index = Index::open(directory::MmapDirectory::open(path)?)?;
+        if index.schema() != schema() {
+            eprintln!("El índice de búsqueda es de una versión anterior; se reconstruye");
+        } else if index.load_metas()?.payload.as_ref() != Some(&fingerprint) {
+            eprintln!(
+                "Los genes cambiaron desde que se creó el índice de búsqueda; se reconstruye"
+            );
+        } else {
+            return Ok(index);
+        }
+
+        fs::remove_dir_all(path)?;
+    }
+
+    fs::create_dir(path)?;
+    let dir = directory::MmapDirectory::open(path)?;
+    let schema = schema();
+    let index = Index::create(dir, schema.clone(), Default::default())?;
+    let mut index_writer = index.writer(50_000_000)?;
+
+    let field = |name| schema.get_field(name).unwrap();
+    let step = (genes.len() / 100).max(1);
+    for (n, gene) in genes.iter().enumerate() {
+        if n % step == 0 {
+            eprintln!("{}%", (100 * n) / genes.len());
+        }
+
+        let mut document = Document::default();
+        for matched in Matched::ALL {
+            let values: Vec<&String> = match matched {
+                Matched::Symbol => vec![&gene.symbol],
+                Matched::Accession => std::iter::once(&gene.uniprot_id)
+                    .chain(&gene.accessions)
+                    .collect(),
+                Matched::Alias => gene.aliases.iter().collect(),
+                Matched::PreviousSymbol => gene.previous_symbols.iter().collect(),
+                Matched::ProteinName => gene.protein_names.iter().collect(),
+            };
+
+            for value in values {
+                document.add_text(field(matched.text()), value);
+                if let Some(exact) = matched.exact() {
+                    document.add_text(field(exact), value.to_lowercase());
+                }
+            }
+        }
+        index_writer.add_document(document)?;
+    }
+    eprintln!("100%");
+
+    eprintln!("Commiting...");
+    let mut commit = index_writer.prepare_commit()?;
+    commit.set_payload(&fingerprint);
+    commit.commit()?;
+    eprintln!("Commited!");
+
+    Ok(index)
+}
+
+/// Regresa los genes que mejor coinciden con la búsqueda, hasta 20. Primero los que tienen
+/// un símbolo, id de uniprot, alias o símbolo previo igual al texto, y después los que
+/// coinciden por texto en cualquier campo, incluido el nombre de la proteína. Si no hay
+/// ninguno, se hace una búsqueda difusa.
+pub fn search(index: &Index, n: &str) -> tantivy::Result<Vec<Hit>> {
+    let schema = index.schema();
     let reader = index
         .reader_builder()
         .reload_policy(ReloadPolicy::OnCommit)
         .try_into()?;
-
     let searcher = reader.searcher();
 
-    let query_parser = QueryParser::for_index(&index, vec![gene]);
-    let query = query_parser.parse_query(n)?;
-    let result = searcher.search(&query, &TopDocs::with_limit(1))?;
+    // Documentos encontrados, con el campo que coincidió y si fue exacto.
+    let mut found: Vec<(DocAddress, Matched, bool)> = Vec::new();
 
-    let top_results = if result.is_empty() {
-        // Si no se encontró que la cadena exacta corresponda a un gen, hace una búsqueda difusa
-        let mut query_parser = QueryParser::for_index(&index, vec![gene]);
-        query_parser.set_field_fuzzy(gene, true, 1, true);
-        let query = query_parser.parse_query(n)?;
+    let text = n.trim().to_lowercase();
+    for matched in Matched::ALL {
+        let Some(exact) = matched.exact() else {
+            continue;
+        };
+        let term = Term::from_field_text(schema.get_field(exact)?, &text);
+        let query = TermQuery::new(term, IndexRecordOption::Basic);
+        for (_, address) in searcher.search(&query, &TopDocs::with_limit(LIMIT))? {
+            found.push((address, matched, true));
+        }
+    }
 
-        searcher
-            .search(&query, &TopDocs::with_limit(20))?
-    } else {
-        result
+    let fields: Vec<Field> = Matched::ALL
+        .iter()
+        .map(|matched| schema.get_field(matched.text()))
+        .collect::<Result<_, _>>()?;
+    // El texto viene de los usuarios: lo que no se puede interpretar con la sintaxis de
+    // Tantivy, como `TP53:` o unas comillas sin cerrar, se ignora en lugar de fallar.
+    let parse = |fields: Vec<Field>, fuzzy: bool| {
+        let mut query_parser = QueryParser::for_index(index, fields.clone());
+        if fuzzy {
+            for field in fields {
+                query_parser.set_field_fuzzy(field, true, 1, true);
+            }
+        }
+
+        query_parser.parse_query_lenient(n).0
     };
 
-    
+    let mut results =
+        searcher.search(&parse(fields.clone(), false), &TopDocs::with_limit(LIMIT))?;
+    // Si no se encontró que la cadena corresponda a un gen, hace una búsqueda difusa
+    let fuzzy = results.is_empty() && found.is_empty();
+    if fuzzy {
+        results = searcher.search(&parse(fields.clone(), true), &TopDocs::with_limit(LIMIT))?;
+    }
 
-    Ok(top_results
+    // El campo que coincidió es el primero cuya consulta, por sí sola, incluye al gen.
+    let per_field: Vec<(Matched, Box<dyn Query>)> = Matched::ALL
         .into_iter()
-        .map(|(_, adress)| {
-            (searcher
-                .doc(adress)
-                .expect("El documento fue proveído por el searcher, es decir, lo contiene")
-                .get_first(uniprot_id)
-                .expect("Todo documento en el index tiene un atributo \"Uniprot_ID\" guardado")
-                .as_text()
-                .expect("Todo atributo gene es un string")
-                .to_string(),
-            searcher
-                .doc(adress)
-                .expect("El documento fue proveído por el searcher, es decir, lo contiene")
-                .get_first(gene)
-                .expect("Todo documento en el index tiene un atributo \"Nombre_Gen\" guardado")
-                .as_text()
-                .expect("Todo atributo gene es un string")
-                .to_string())
-        })
-        .collect())
+        .zip(fields)
+        .map(|(matched, field)| (matched, parse(vec![field], fuzzy)))
+        .collect();
+    for (_, address) in results {
+        let matched = per_field
+            .iter()
+            .find(|(_, query)| query.explain(&searcher, address).is_ok())
+            .map_or(Matched::Symbol, |(matched, _)| *matched);
+        found.push((address, matched, false));
+    }
+
+    let mut hits: Vec<Hit> = Vec::new();
+    let mut seen: Vec<DocAddress> = Vec::new();
+    for (address, matched, exact) in found {
+        if seen.contains(&address) || hits.len() == LIMIT {
+            continue;
+        }
+        seen.push(address);
+
+        let document = searcher.doc(address)?;
+        let text = |name| {
+            document
+                .get_first(schema.get_field(name).unwrap())
+                .and_then(|value| value.as_text())
+                .unwrap_or_default()
+                .to_owned()
+        };
+        hits.push(Hit {
+            uniprot_id: text(Matched::Accession.text()),
+            gene: text(Matched::Symbol.text()),
+            matched,
+            exact,
+        });
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_every_field_with_exact_hits_first() {
+        let path = std::env::temp_dir().join(format!("search-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let genes = [
+            GeneInfo {
+                uniprot_id: "P04637".into(),
+                symbol: "TP53".into(),
+                protein_names: vec!["Cellular tumor antigen p53".into()],
+                aliases: vec!["LFS1".into()],
+                accessions: vec!["Q15086".into()],
+                ..GeneInfo::default()
+            },
+            GeneInfo {
+                uniprot_id: "O15350".into(),
+                symbol: "TP73".into(),
+                protein_names: vec!["Tumor protein p73".into()],
+                previous_symbols: vec!["P73".into()],
+                ..GeneInfo::default()
+            },
+            GeneInfo {
+                uniprot_id: "Q00001".into(),
+                symbol: "P53BP".into(),
+                ..GeneInfo::default()
+            },
+        ];
+        let index = index(&path, &genes).unwrap();
+
+        let hit = |text: &str| search(&index, text).unwrap();
+        assert_eq!(hit("P04637")[0].gene, "TP53");
+        assert_eq!(hit("p04637")[0].matched, Matched::Accession);
+        assert_eq!(hit("Q15086")[0].uniprot_id, "P04637");
+        assert_eq!(hit("lfs1")[0].matched, Matched::Alias);
+        assert!(hit("lfs1")[0].exact);
+        assert_eq!(hit("P73")[0].matched, Matched::PreviousSymbol);
+
+        let hits = hit("tumor");
+        assert_eq!(hits.len(), 2);
+        assert!(hits
+            .iter()
+            .all(|hit| hit.matched == Matched::ProteinName && !hit.exact));
+
+        let fuzzy = hit("TP35");
+        assert!(!fuzzy.is_empty());
+        assert!(fuzzy.iter().all(|hit| hit.matched == Matched::Symbol));
+
+        // La sintaxis de Tantivy que no se puede interpretar no es un error.
+        for text in ["TP53:", "\"TP53", "(tumor", "Nombre_Gen:"] {
+            assert!(search(&index, text).is_ok(), "{}", text);
+        }
+
+        // Con los mismos genes, el índice se reabre; si cambian, se reconstruye.
+        drop(index);
+        let index = super::index(&path, &genes).unwrap();
+        assert_eq!(search(&index, "O15350").unwrap()[0].gene, "TP73");
+        drop(index);
+        let index = super::index(&path, &genes[..1]).unwrap();
+        assert_eq!(search(&index, "TP53").unwrap()[0].uniprot_id, "P04637");
+        assert!(search(&index, "O15350").unwrap().is_empty());
+        fs::remove_dir_all(&path).unwrap();
+    }
 }
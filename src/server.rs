@@ -0,0 +1,1059 @@
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rocket::{
+    catch, catchers,
+    data::{Data, Limits, ToByteUnit},
+    figment::Figment,
+    fs::FileServer,
+    get,
+    http::{ContentType, Status},
+    post,
+    request::Request,
+    routes,
+    serde::json::Json,
+    tokio::task,
+    Build, Rocket, State,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aminoacids::AminoAcid,
+    config::Config,
+    database::{DataBase, Metadata, QueryError},
+    features::{self, FeatureScore},
+    genes,
+    genomic::{Assembly, Genome, GenomicError},
+    heatmap::{Heatmap, HeatmapOptions},
+    isoforms::Isoforms,
+    proteins::{Entry, Feature, ProteinStore},
+    search,
+    summary::{GeneMeans, Summary},
+    svg,
+    variations::{AmClass, Variation, VariationError},
+    vcf::{self, VcfError},
+};
+
+#[get("/world")]
+fn world() -> &'static str {
+    "Hello, world!"
+}
+
+/// Gen encontrado por la búsqueda.
+#[derive(Serialize, Debug)]
+struct Found {
+    uniprot_id: String,
+    gene: String,
+    /// Campo que coincidió con la búsqueda.
+    matched: search::Matched,
+    exact: bool,
+    /// Transcritos del gen en la base de datos de isoformas.
+    isoforms: Vec<String>,
+    /// Resumen de las predicciones, si se pide con `?summary=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<Summary>,
+}
+
+#[get("/<name>?<summary>")]
+fn query(
+    name: &str,
+    summary: Option<bool>,
+    index: &State<tantivy::Index>,
+    database: &State<DataBase>,
+    isoforms: &State<Option<Isoforms>>,
+    means: &State<Option<GeneMeans>>,
+) -> Result<Json<Vec<Found>>, Failure> {
+    let resultados = search::search(index, name)
+        .map_err(|e| failure(Status::InternalServerError, "index_error", e))?;
+
+    Ok(Json(
+        resultados
+            .into_iter()
+            .map(|hit| Found {
+                isoforms: isoforms
+                    .iter()
+                    .flat_map(|isoforms| isoforms.isoforms(&hit.gene))
+                    .map(str::to_owned)
+                    .collect(),
+                summary: summary
+                    .unwrap_or(false)
+                    .then(|| database.summary(&hit.uniprot_id, means.as_ref()).ok())
+                    .flatten(),
+                uniprot_id: hit.uniprot_id,
+                gene: hit.gene,
+                matched: hit.matched,
+                exact: hit.exact,
+            })
+            .collect(),
+    ))
+}
+
+#[get("/")]
+fn meta(database: &State<DataBase>) -> Json<&Metadata> {
+    Json(database.meta())
+}
+
+/// Predicción de una variación, como la regresa la API.
+#[derive(Serialize, Debug)]
+struct Prediction {
+    /// Ausente en las predicciones de `/isoforms`, que llevan el transcrito.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uniprot_id: Option<String>,
+    gene: Option<String>,
+    reference: char,
+    position: u16,
+    alternate: char,
+    score: Option<f32>,
+    /// `benign`, `ambiguous`, `pathogenic`, `undefined`, o `synonymous` si la variación
+    /// no cambia el aminoácido.
+    class: &'static str,
+    /// Regiones anotadas en UniProtKB que abarcan la posición, si el servidor tiene las
+    /// entradas de UniProtKB.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    features: Vec<Feature>,
+}
+
+/// Error de la API: un código fijo para otros programas y un mensaje para personas.
+#[derive(Serialize, Debug, Clone)]
+struct ApiError {
+    error: &'static str,
+    message: String,
+    /// Aminoácido de la proteína, cuando la referencia de la variación no coincide.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<char>,
+}
+
+type Failure = (Status, Json<ApiError>);
+
+fn failure(status: Status, error: &'static str, message: impl ToString) -> Failure {
+    let message = message.to_string();
+    (
+        status,
+        Json(ApiError {
+            error,
+            message,
+            expected: None,
+        }),
+    )
+}
+
+impl From<VariationError> for Failure {
+    fn from(e: VariationError) -> Self {
+        match e {
+            VariationError::Unsupported { .. } | VariationError::Nonsense => {
+                failure(Status::BadRequest, "unsupported_variant", e)
+            }
+            _ => failure(Status::BadRequest, "malformed_variant", e),
+        }
+    }
+}
+
+impl From<QueryError> for Failure {
+    fn from(e: QueryError) -> Self {
+        match e {
+            QueryError::UnknownProtein { .. } => failure(Status::NotFound, "unknown_protein", e),
+            QueryError::UnknownPosition { .. } => failure(Status::NotFound, "unknown_position", e),
+            QueryError::Corrupt { .. } => failure(Status::InternalServerError, "database_error", e),
+            QueryError::ReferenceMismatch { expected, .. } => {
+                let (status, Json(mut error)) =
+                    failure(Status::UnprocessableEntity, "reference_mismatch", e);
+                error.expected = Some(AminoAcid::SINGLE_LETTER_CODE[expected]);
+                (status, Json(error))
+            }
+        }
+    }
+}
+
+impl Prediction {
+    fn new(uniprot_id: &str, variation: &Variation, class: AmClass) -> Self {
+        Prediction {
+            uniprot_id: Some(uniprot_id.to_owned()),
+            gene: genes::name(uniprot_id).map(str::to_owned),
+            ..Prediction::isoform(None, variation, class)
+        }
+    }
+
+    fn isoform(gene: Option<&str>, variation: &Variation, class: AmClass) -> Self {
+        Prediction {
+            uniprot_id: None,
+            gene: gene.map(str::to_owned),
+            reference: AminoAcid::SINGLE_LETTER_CODE[variation.base],
+            position: variation.position,
+            alternate: AminoAcid::SINGLE_LETTER_CODE[variation.variant],
+            score: class.score(),
+            class: match variation.is_synonymous() {
+                true => "synonymous",
+                false => class.name(),
+            },
+            features: Vec::new(),
+        }
+    }
+}
+
+/// Entrada de UniProtKB de `uniprot_id`, si el servidor tiene las entradas y la proteína
+/// está en ellas.
+fn entry(proteins: Option<&ProteinStore>, uniprot_id: &str) -> Result<Option<Entry>, Failure> {
+    match proteins {
+        Some(proteins) => proteins
+            .get(uniprot_id)
+            .map_err(|e| failure(Status::InternalServerError, "index_error", e)),
+        None => Ok(None),
+    }
+}
+
+/// Interpreta una variación en `protein`, que puede ser un id de uniprot o el símbolo de
+/// un gen. Regresa el id de uniprot de la proteína.
+fn parse<'a>(
+    database: &DataBase,
+    protein: &'a str,
+    variant: &str,
+) -> Result<(&'a str, Variation), Failure> {
+    let variation = Variation::from_str(variant)?;
+    let uniprot_id = database
+        .resolve(protein)
+        .ok_or_else(|| QueryError::UnknownProtein {
+            uniprot_id: protein.to_owned(),
+        })?;
+
+    Ok((uniprot_id, variation))
+}
+
+#[get("/<id>/<variant>")]
+fn get_variants(
+    id: &str,
+    variant: &str,
+    database: &State<DataBase>,
+    proteins: &State<Option<ProteinStore>>,
+) -> Result<Json<Prediction>, Failure> {
+    let (uniprot_id, variation) = parse(database, id, variant)?;
+    let class = database.query(uniprot_id, &variation)?;
+    let features = entry(proteins.as_ref(), uniprot_id)?
+        .map(|entry| features::covering(&entry, variation.position))
+        .unwrap_or_default();
+
+    Ok(Json(Prediction {
+        features,
+        ..Prediction::new(uniprot_id, &variation, class)
+    }))
+}
+
+/// Elemento de una consulta por lotes: un objeto `{"protein": ..., "variant": ...}` o un
+/// par `[protein, variant]`.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum BatchItem {
+    Object {
+        #[serde(alias = "uniprot_id", alias = "gene")]
+        protein: String,
+        variant: String,
+    },
+    Pair(String, String),
+}
+
+/// Resultado de un elemento de una consulta por lotes.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum BatchResult {
+    Prediction(Prediction),
+    Error(ApiError),
+}
+
+/// Evalúa todas las variaciones de una consulta por lotes, en orden. Los errores se
+/// reportan por elemento.
+fn batch(
+    database: &DataBase,
+    proteins: Option<&ProteinStore>,
+    items: Vec<Result<(String, String), Failure>>,
+) -> Vec<BatchResult> {
+    let parsed: Vec<_> = items
+        .iter()
+        .map(|item| {
+            let (protein, variant) = item.as_ref().map_err(Clone::clone)?;
+            parse(database, protein, variant)
+        })
+        .collect();
+
+    let mut classes = database
+        .query_batch(
+            parsed
+                .iter()
+                .flatten()
+                .map(|(id, variation)| (*id, variation)),
+        )
+        .into_iter();
+    // Cada entrada de UniProtKB se decodifica una sola vez.
+    let mut entries: HashMap<&str, Result<Option<Entry>, Failure>> = HashMap::new();
+
+    parsed
+        .iter()
+        .map(|item| {
+            let (uniprot_id, variation) = item.as_ref().map_err(Clone::clone)?;
+            let class = classes
+                .next()
+                .expect("Hay una clasificación por cada variación")?;
+            let features = entries
+                .entry(uniprot_id)
+                .or_insert_with(|| entry(proteins, uniprot_id))
+                .as_ref()
+                .map_err(Clone::clone)?
+                .as_ref()
+                .map(|entry| features::covering(entry, variation.position))
+                .unwrap_or_default();
+
+            Ok(Prediction {
+                features,
+                ..Prediction::new(uniprot_id, variation, class)
+            })
+        })
+        .map(|result: Result<Prediction, Failure>| match result {
+            Ok(prediction) => BatchResult::Prediction(prediction),
+            Err((_, Json(error))) => BatchResult::Error(error),
+        })
+        .collect()
+}
+
+#[post("/", format = "json", data = "<items>", rank = 1)]
+fn post_variants_json(
+    items: Json<Vec<serde_json::Value>>,
+    database: &State<DataBase>,
+    proteins: &State<Option<ProteinStore>>,
+) -> Json<Vec<BatchResult>> {
+    // Cada elemento se interpreta por separado, para que uno mal formado no invalide a
+    // toda la consulta.
+    let items = items
+        .into_inner()
+        .into_iter()
+        .map(|item| match serde_json::from_value(item) {
+            Ok(BatchItem::Object { protein, variant } | BatchItem::Pair(protein, variant)) => {
+                Ok((protein, variant))
+            }
+            Err(_) => Err(failure(
+                Status::BadRequest,
+                "malformed_item",
+                "El elemento no es un objeto con `protein` y `variant` ni un par de textos",
+            )),
+        })
+        .collect();
+
+    Json(batch(database, proteins.as_ref(), items))
+}
+
+/// Consulta por lotes en TSV: una variación por línea, como `TP53\tR175H`. Se ignoran
+/// las líneas vacías y las que inician con `#`.
+#[post("/", data = "<tsv>", rank = 2)]
+async fn post_variants_tsv(
+    tsv: Data<'_>,
+    limits: &Limits,
+    database: &State<DataBase>,
+    proteins: &State<Option<ProteinStore>>,
+) -> Result<Json<Vec<BatchResult>>, Failure> {
+    let tsv = tsv
+        .open(limits.get("tsv").unwrap_or(1.mebibytes()))
+        .into_string()
+        .await
+        .map_err(|e| failure(Status::BadRequest, "malformed_body", e))?;
+    if !tsv.is_complete() {
+        return Err(failure(
+            Status::PayloadTooLarge,
+            "payload_too_large",
+            "La consulta excede el límite `tsv` de la configuración",
+        ));
+    }
+
+    let items = tsv
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| match line.trim_end_matches('\r').split_once('\t') {
+            Some((protein, variant)) => Ok((protein.trim().to_owned(), variant.trim().to_owned())),
+            None => Err(failure(
+                Status::BadRequest,
+                "malformed_line",
+                format!(
+                    "{:?} no tiene dos columnas separadas por un tabulador",
+                    line
+                ),
+            )),
+        })
+        .collect();
+
+    Ok(Json(batch(database, proteins.as_ref(), items)))
+}
+
+/// Resumen de las predicciones de una proteína o de una isoforma.
+#[derive(Serialize, Debug)]
+struct GeneSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uniprot_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transcript_id: Option<String>,
+    gene: Option<String>,
+    #[serde(flatten)]
+    summary: Summary,
+    /// Patogenicidad media en cada región anotada en UniProtKB, si el servidor tiene las
+    /// entradas de UniProtKB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    features: Option<Vec<FeatureScore>>,
+}
+
+/// Resumen de las predicciones de `protein`: un id de uniprot, el símbolo de un gen o, si
+/// el servidor tiene la base de datos de isoformas, un transcrito.
+#[get("/<protein>")]
+fn get_summary(
+    protein: &str,
+    database: &State<DataBase>,
+    isoforms: &State<Option<Isoforms>>,
+    means: &State<Option<GeneMeans>>,
+    proteins: &State<Option<ProteinStore>>,
+) -> Result<Json<GeneSummary>, Failure> {
+    let protein = Protein::locate(protein, database, isoforms);
+    let features = match protein.isoform {
+        true => None,
+        false => entry(proteins.as_ref(), protein.id)?
+            .map(|entry| protein.database.feature_scores(protein.id, &entry))
+            .transpose()?,
+    };
+
+    Ok(Json(GeneSummary {
+        summary: protein.database.summary(protein.id, means.as_ref())?,
+        features,
+        uniprot_id: protein.uniprot_id(),
+        transcript_id: protein.transcript_id(),
+        gene: protein.gene,
+    }))
+}
+
+/// Matriz de patogenicidades de una proteína, con sus metadatos.
+#[derive(Serialize, Debug)]
+struct GeneHeatmap {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uniprot_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transcript_id: Option<String>,
+    gene: Option<String>,
+    #[serde(flatten)]
+    heatmap: Heatmap,
+}
+
+/// Matriz de patogenicidades de `protein`, que se indica como en `/summary`. Se puede
+/// limitar a las posiciones entre `start` y `end`, y reducir a `rows` filas.
+#[get("/<protein>?<start>&<end>&<rows>")]
+fn get_heatmap(
+    protein: &str,
+    start: Option<u16>,
+    end: Option<u16>,
+    rows: Option<usize>,
+    database: &State<DataBase>,
+    isoforms: &State<Option<Isoforms>>,
+) -> Result<Json<GeneHeatmap>, Failure> {
+    let protein = Protein::locate(protein, database, isoforms);
+    let options = HeatmapOptions { start, end, rows };
+
+    Ok(Json(GeneHeatmap {
+        heatmap: protein.database.heatmap(protein.id, &options)?,
+        uniprot_id: protein.uniprot_id(),
+        transcript_id: protein.transcript_id(),
+        gene: protein.gene,
+    }))
+}
+
+/// Dibuja como SVG las predicciones de `protein`, que se indica como en `/summary`.
+/// `plot` es `heatmap`, `profile` o `figure` (las dos, por omisión), y `variants` una
+/// lista de variaciones separadas por comas que se marcan en la gráfica. La matriz se
+/// reduce a `rows` filas, o a [`svg::MAX_ROWS`] si no se indica.
+#[allow(clippy::too_many_arguments)]
+#[get("/<protein>?<plot>&<variants>&<start>&<end>&<rows>")]
+fn get_svg(
+    protein: &str,
+    plot: Option<&str>,
+    variants: Option<&str>,
+    start: Option<u16>,
+    end: Option<u16>,
+    rows: Option<usize>,
+    database: &State<DataBase>,
+    isoforms: &State<Option<Isoforms>>,
+) -> Result<(ContentType, String), Failure> {
+    let plot = match plot.map(svg::Plot::from_str) {
+        None => svg::Plot::Figure,
+        Some(Ok(plot)) => plot,
+        Some(Err(e)) => return Err(failure(Status::BadRequest, "unknown_plot", e)),
+    };
+    let markers = variants
+        .into_iter()
+        .flat_map(|variants| variants.split(','))
+        .filter(|variant| !variant.trim().is_empty())
+        .map(|variant| Variation::from_str(variant.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let protein = Protein::locate(protein, database, isoforms);
+    let options = HeatmapOptions {
+        start,
+        end,
+        rows: Some(rows.unwrap_or(svg::MAX_ROWS)),
+    };
+    let heatmap = protein.database.heatmap(protein.id, &options)?;
+    let title = match &protein.gene {
+        Some(gene) => format!("{} ({})", gene, protein.id),
+        None => protein.id.to_owned(),
+    };
+
+    Ok((
+        ContentType::SVG,
+        svg::render(&heatmap, plot, &title, &markers),
+    ))
+}
+
+/// Proteína o isoforma a la que se refiere una ruta.
+struct Protein<'a> {
+    database: &'a DataBase,
+    /// Id en `database`.
+    id: &'a str,
+    isoform: bool,
+    gene: Option<String>,
+}
+
+impl<'a> Protein<'a> {
+    /// Busca `protein`, que puede ser un id de uniprot, el símbolo de un gen o, si el
+    /// servidor tiene la base de datos de isoformas, un transcrito.
+    fn locate(protein: &'a str, database: &'a DataBase, isoforms: &'a Option<Isoforms>) -> Self {
+        let isoform = isoforms
+            .as_ref()
+            .and_then(|isoforms| Some((isoforms, isoforms.resolve(protein)?)));
+
+        match isoform {
+            Some((isoforms, transcript)) => Protein {
+                database: isoforms.database(),
+                id: transcript,
+                isoform: true,
+                gene: isoforms.gene(transcript).map(str::to_owned),
+            },
+            None => {
+                let uniprot_id = database.resolve(protein).unwrap_or(protein);
+                Protein {
+                    database,
+                    id: uniprot_id,
+                    isoform: false,
+                    gene: genes::name(uniprot_id).map(str::to_owned),
+                }
+            }
+        }
+    }
+
+    fn uniprot_id(&self) -> Option<String> {
+        (!self.isoform).then(|| self.id.to_owned())
+    }
+
+    fn transcript_id(&self) -> Option<String> {
+        self.isoform.then(|| self.id.to_owned())
+    }
+}
+
+/// Isoformas de un gen.
+#[derive(Serialize, Debug)]
+struct GeneIsoforms {
+    gene: Option<String>,
+    isoforms: Vec<String>,
+}
+
+/// Resultado de una variación en una isoforma.
+#[derive(Serialize, Debug)]
+struct IsoformResult {
+    transcript_id: String,
+    #[serde(flatten)]
+    result: BatchResult,
+}
+
+fn isoforms_of<'a>(
+    isoforms: &'a Option<Isoforms>,
+    gene: &str,
+) -> Result<(&'a Isoforms, Vec<&'a str>), Failure> {
+    let Some(isoforms) = isoforms else {
+        return Err(failure(
+            Status::ServiceUnavailable,
+            "isoforms_unavailable",
+            "El servidor no tiene la base de datos de isoformas",
+        ));
+    };
+
+    match isoforms.isoforms(gene) {
+        transcripts if transcripts.is_empty() => Err(failure(
+            Status::NotFound,
+            "unknown_gene",
+            format!("No se conocen isoformas de {}", gene),
+        )),
+        transcripts => Ok((isoforms, transcripts)),
+    }
+}
+
+/// Lista las isoformas de un gen, por su símbolo, su id de uniprot o uno de sus
+/// transcritos.
+#[get("/<gene>")]
+fn get_isoforms(
+    gene: &str,
+    isoforms: &State<Option<Isoforms>>,
+) -> Result<Json<GeneIsoforms>, Failure> {
+    let (isoforms, transcripts) = isoforms_of(isoforms, gene)?;
+
+    Ok(Json(GeneIsoforms {
+        gene: isoforms.gene(transcripts[0]).map(str::to_owned),
+        isoforms: transcripts.into_iter().map(str::to_owned).collect(),
+    }))
+}
+
+/// Evalúa una variación en cada isoforma de un gen.
+#[get("/<gene>/<variant>")]
+fn get_isoform_variants(
+    gene: &str,
+    variant: &str,
+    isoforms: &State<Option<Isoforms>>,
+) -> Result<Json<Vec<IsoformResult>>, Failure> {
+    let variation = Variation::from_str(variant)?;
+    let (isoforms, _) = isoforms_of(isoforms, gene)?;
+
+    Ok(Json(
+        isoforms
+            .query_gene(gene, &variation)
+            .into_iter()
+            .map(|(transcript, class)| IsoformResult {
+                transcript_id: transcript.to_owned(),
+                result: match class {
+                    Ok(class) => BatchResult::Prediction(Prediction::isoform(
+                        isoforms.gene(transcript),
+                        &variation,
+                        class,
+                    )),
+                    Err(e) => {
+                        let (_, Json(error)) = Failure::from(e);
+                        BatchResult::Error(error)
+                    }
+                },
+            })
+            .collect(),
+    ))
+}
+
+/// Nombres, secuencia, ids secundarios y regiones anotadas de `protein`, un id de uniprot o
+/// el símbolo de un gen, tomados del volcado de UniProtKB.
+#[get("/<protein>")]
+fn get_protein(
+    protein: &str,
+    database: &State<DataBase>,
+    proteins: &State<Option<ProteinStore>>,
+) -> Result<Json<Entry>, Failure> {
+    let Some(proteins) = proteins.inner() else {
+        return Err(failure(
+            Status::ServiceUnavailable,
+            "proteins_unavailable",
+            "El servidor no tiene las entradas de UniProtKB; constrúyalas con build-proteins",
+        ));
+    };
+    let uniprot_id = database.resolve(protein).unwrap_or(protein);
+
+    match proteins.get(uniprot_id) {
+        Ok(Some(entry)) => Ok(Json(entry)),
+        Ok(None) => Err(failure(
+            Status::NotFound,
+            "unknown_protein",
+            format!("No hay entrada de UniProtKB para {}", protein),
+        )),
+        Err(e) => Err(failure(Status::InternalServerError, "index_error", e)),
+    }
+}
+
+/// Anota un VCF, comprimido con bgzip o no, con las predicciones del índice genómico.
+/// `assembly` es el ensamble de sus coordenadas, `hg38` si no se indica.
+#[post("/?<assembly>", data = "<vcf>")]
+async fn annotate(
+    assembly: Option<&str>,
+    vcf: Data<'_>,
+    limits: &Limits,
+    genome: &State<Arc<Genome>>,
+) -> Result<(ContentType, Vec<u8>), Failure> {
+    let assembly: Assembly = assembly
+        .unwrap_or("hg38")
+        .parse()
+        .map_err(|e| failure(Status::BadRequest, "unknown_assembly", e))?;
+    if !genome.assemblies().contains(&assembly) {
+        return Err(failure(
+            Status::ServiceUnavailable,
+            "genome_unavailable",
+            GenomicError::Unavailable { assembly },
+        ));
+    }
+
+    let vcf = vcf
+        .open(limits.get("vcf").unwrap_or(64.mebibytes()))
+        .into_bytes()
+        .await
+        .map_err(|e| failure(Status::BadRequest, "malformed_body", e))?;
+    if !vcf.is_complete() {
+        return Err(failure(
+            Status::PayloadTooLarge,
+            "payload_too_large",
+            "El VCF excede el límite `vcf` de la configuración",
+        ));
+    }
+
+    // La anotación lee todo el VCF y consulta el índice: se hace fuera de los hilos de
+    // Rocket para no detener las demás peticiones.
+    let genome = Arc::clone(genome);
+    let annotated = task::spawn_blocking(move || {
+        let mut output = Vec::new();
+        vcf::annotate(
+            &genome,
+            assembly,
+            io::Cursor::new(vcf.into_inner()),
+            &mut output,
+        )
+        .map(|_| output)
+    })
+    .await
+    .map_err(|e| failure(Status::InternalServerError, "internal_error", e))?;
+
+    match annotated {
+        Ok(output) => Ok((ContentType::Plain, output)),
+        Err(e @ (VcfError::Malformed { .. } | VcfError::Io(_))) => {
+            Err(failure(Status::BadRequest, "malformed_vcf", e))
+        }
+        Err(e @ VcfError::DeclaredAssembly { .. }) => {
+            Err(failure(Status::UnprocessableEntity, "assembly_mismatch", e))
+        }
+        Err(e @ VcfError::Genome(_)) => Err(failure(Status::InternalServerError, "index_error", e)),
+    }
+}
+
+#[catch(400)]
+fn bad_request(request: &Request) -> Failure {
+    failure(
+        Status::BadRequest,
+        "bad_request",
+        format!("La petición a {} no es válida", request.uri()),
+    )
+}
+
+#[catch(404)]
+fn not_found(request: &Request) -> Failure {
+    failure(
+        Status::NotFound,
+        "not_found",
+        format!("No existe la ruta {}", request.uri()),
+    )
+}
+
+#[catch(422)]
+fn unprocessable_entity(request: &Request) -> Failure {
+    failure(
+        Status::UnprocessableEntity,
+        "unprocessable_entity",
+        format!(
+            "El cuerpo de la petición a {} no tiene la forma esperada",
+            request.uri()
+        ),
+    )
+}
+
+/// Construye el servidor: abre, o construye, la base de datos y el índice de búsqueda
+/// indicados en `config`, y usa `figment` como configuración de Rocket.
+pub fn rocket(figment: Figment, config: &Config) -> Rocket<Build> {
+    let database = DataBase::open(config).unwrap_or_else(|e| panic!("{}", e));
+    let genes = genes::open(config).unwrap_or_else(|e| panic!("{}", e));
+    let index =
+        search::index(&config.index, &genes).expect("El programa necesita que se inicie Tanitvy");
+    let genome = Genome::open(config).unwrap_or_else(|e| panic!("{}", e));
+    let isoforms = Isoforms::open(config).unwrap_or_else(|e| panic!("{}", e));
+    let means = GeneMeans::open(config, &genome);
+    let proteins = ProteinStore::open(config).unwrap_or_else(|e| panic!("{}", e));
+    if genome.assemblies().is_empty() {
+        eprintln!(
+            "No se encontró ningún índice genómico ni las tablas para construirlos; \
+             /annotate no estará disponible"
+        );
+    }
+
+    rocket::custom(figment)
+        .manage(database)
+        .manage(index)
+        .manage(Arc::new(genome))
+        .manage(isoforms)
+        .manage(means)
+        .manage(proteins)
+        .mount("/", FileServer::from(&config.frontend))
+        .mount("/hello", routes![world])
+        .mount("/search", routes![query])
+        .mount("/meta", routes![meta])
+        .mount(
+            "/variants",
+            routes![get_variants, post_variants_json, post_variants_tsv],
+        )
+        .mount("/annotate", routes![annotate])
+        .mount("/isoforms", routes![get_isoforms, get_isoform_variants])
+        .mount("/summary", routes![get_summary])
+        .mount("/heatmap", routes![get_heatmap])
+        .mount("/svg", routes![get_svg])
+        .mount("/proteins", routes![get_protein])
+        .register("/", catchers![bad_request, not_found, unprocessable_entity])
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::local::blocking::{Client, LocalResponse};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        database,
+        genomic::{self, GenomicIndex},
+        proteins,
+    };
+
+    /// Cliente de un servidor con una base de datos de TP53, el índice genómico de hg38 y
+    /// las entradas de UniProtKB, sin isoformas. `vcf` y `tsv` limitan las consultas a
+    /// 256 bytes.
+    fn client(name: &str) -> Client {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let hg38 =
+            genomic::tests::synthetic_table(&format!("{}-hg38", name), &genomic::tests::ROWS);
+        GenomicIndex::build(&hg38, &dir.join("hg38.cdv"), Assembly::Hg38, false).unwrap();
+        let dump = dir.join("uniprot.dat");
+        std::fs::write(&dump, proteins::tests::FLAT).unwrap();
+        ProteinStore::build(&dump, &dir.join("proteins.cdv"), None).unwrap();
+
+        let config = Config {
+            database: dir.join("variations.cdv"),
+            input: Some(database::tests::substitutions_table(
+                name,
+                "uniprot_id",
+                &[
+                    "P04637 M1A 0.1 benign",
+                    "P04637 R175H 0.9977 pathogenic",
+                    "P04637 R175C 0.3001 benign",
+                ],
+            )),
+            index: dir.join("index"),
+            hg38: dir.join("hg38.cdv"),
+            hg19: dir.join("hg19.cdv"),
+            isoforms: dir.join("isoforms.cdv"),
+            names: dir.join("names.json"),
+            gene_info: dir.join("genes.jsonl"),
+            proteins: dir.join("proteins.cdv"),
+            frontend: dir.clone(),
+            ..Config::default()
+        };
+        let figment = rocket::Config::figment()
+            .merge(("log_level", rocket::config::LogLevel::Off))
+            .merge((
+                "limits",
+                Limits::default()
+                    .limit("vcf", 256.bytes())
+                    .limit("tsv", 256.bytes()),
+            ));
+
+        Client::tracked(rocket(figment, &config)).unwrap()
+    }
+
+    /// Borra los archivos de [`client`].
+    fn remove(name: &str) {
+        let dir = std::env::temp_dir();
+        std::fs::remove_dir_all(dir.join(format!("{}-{}", name, std::process::id()))).unwrap();
+        std::fs::remove_file(dir.join(format!("{}-{}.tsv", name, std::process::id()))).unwrap();
+        std::fs::remove_file(dir.join(format!("{}-hg38-{}.tsv", name, std::process::id())))
+            .unwrap();
+    }
+
+    /// Estado y cuerpo en JSON de una respuesta.
+    fn json(response: LocalResponse) -> (Status, Value) {
+        (response.status(), response.into_json().unwrap())
+    }
+
+    /// Estado y código de error de una respuesta.
+    fn error(response: LocalResponse) -> (Status, String) {
+        let (status, body) = json(response);
+        (status, body["error"].as_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn variant_errors_are_typed() {
+        let client = client("servidor-variantes");
+
+        let response = client.get("/meta").dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        assert!(json(response).1["sha256"].is_string());
+
+        let (status, body) = json(client.get("/variants/TP53/R175H").dispatch());
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["class"], "pathogenic");
+        assert_eq!(body["features"][0]["kind"], "domain");
+
+        let get = |uri: &str| error(client.get(uri.to_owned()).dispatch());
+        assert_eq!(
+            get("/variants/TP53/R175"),
+            (Status::BadRequest, "malformed_variant".into())
+        );
+        assert_eq!(
+            get("/variants/TP53/R175*"),
+            (Status::BadRequest, "unsupported_variant".into())
+        );
+        assert_eq!(
+            get("/variants/Q99999/R175H"),
+            (Status::NotFound, "unknown_protein".into())
+        );
+        assert_eq!(
+            get("/variants/TP53/R200H"),
+            (Status::NotFound, "unknown_position".into())
+        );
+        let (status, body) = json(client.get("/variants/TP53/K175H").dispatch());
+        assert_eq!(status, Status::UnprocessableEntity);
+        assert_eq!(body["error"], "reference_mismatch");
+        assert_eq!(body["expected"], "R");
+
+        let response = client
+            .post("/variants")
+            .header(ContentType::JSON)
+            .body(json!([["TP53", "R175H"], 5, {"protein": "TP53", "variant": "R175"}]).to_string())
+            .dispatch();
+        let (status, body) = json(response);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body[0]["class"], "pathogenic");
+        assert_eq!(body[1]["error"], "malformed_item");
+        assert_eq!(body[2]["error"], "malformed_variant");
+
+        let response = client
+            .post("/variants")
+            .header(ContentType::JSON)
+            .body(json!({"protein": "TP53", "variant": "R175H"}).to_string())
+            .dispatch();
+        assert_eq!(
+            error(response),
+            (Status::UnprocessableEntity, "unprocessable_entity".into())
+        );
+        assert_eq!(
+            error(client.get("/variants").dispatch()),
+            (Status::NotFound, "not_found".into())
+        );
+
+        let response = client
+            .post("/variants")
+            .header(ContentType::Plain)
+            .body("TP53\tR175C\nTP53 R175H\n")
+            .dispatch();
+        let (status, body) = json(response);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body[0]["class"], "benign");
+        assert_eq!(body[1]["error"], "malformed_line");
+
+        let response = client
+            .post("/variants")
+            .header(ContentType::Plain)
+            .body("TP53\tR175H\n".repeat(32))
+            .dispatch();
+        assert_eq!(
+            error(response),
+            (Status::PayloadTooLarge, "payload_too_large".into())
+        );
+
+        remove("servidor-variantes");
+    }
+
+    #[test]
+    fn annotate_errors_are_typed() {
+        let client = client("servidor-anotacion");
+        let annotate = |uri: &str, vcf: String| client.post(uri.to_owned()).body(vcf).dispatch();
+        let header = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+        let row = "chr17\t7675088\t.\tC\tT\t.\t.\t.\n";
+
+        let response = annotate("/annotate", format!("{}{}", header, row));
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("R175H"));
+
+        assert_eq!(
+            error(annotate(
+                "/annotate?assembly=hg17",
+                format!("{}{}", header, row)
+            )),
+            (Status::BadRequest, "unknown_assembly".into())
+        );
+        assert_eq!(
+            error(annotate(
+                "/annotate?assembly=hg19",
+                format!("{}{}", header, row)
+            )),
+            (Status::ServiceUnavailable, "genome_unavailable".into())
+        );
+        assert_eq!(
+            error(annotate(
+                "/annotate",
+                format!("{}{}", header, row.repeat(16))
+            )),
+            (Status::PayloadTooLarge, "payload_too_large".into())
+        );
+        assert_eq!(
+            error(annotate(
+                "/annotate",
+                format!("##reference=GRCh37\n{}{}", header, row)
+            )),
+            (Status::UnprocessableEntity, "assembly_mismatch".into())
+        );
+        assert_eq!(
+            error(annotate("/annotate", format!("{}chr17\t7675088\n", header))),
+            (Status::BadRequest, "malformed_vcf".into())
+        );
+
+        remove("servidor-anotacion");
+    }
+
+    #[test]
+    fn protein_routes_report_errors() {
+        let client = client("servidor-proteinas");
+        let get = |uri: &str| error(client.get(uri.to_owned()).dispatch());
+
+        assert_eq!(
+            get("/isoforms/TP53"),
+            (Status::ServiceUnavailable, "isoforms_unavailable".into())
+        );
+        assert_eq!(
+            get("/isoforms/TP53/R175H"),
+            (Status::ServiceUnavailable, "isoforms_unavailable".into())
+        );
+
+        let (status, body) = json(client.get("/summary/TP53").dispatch());
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["uniprot_id"], "P04637");
+        assert_eq!(
+            get("/summary/Q99999"),
+            (Status::NotFound, "unknown_protein".into())
+        );
+        assert_eq!(
+            get("/heatmap/Q99999"),
+            (Status::NotFound, "unknown_protein".into())
+        );
+
+        let response = client.get("/svg/TP53?variants=R175H").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::SVG));
+        assert_eq!(
+            get("/svg/TP53?plot=pie"),
+            (Status::BadRequest, "unknown_plot".into())
+        );
+        assert_eq!(
+            get("/svg/TP53?variants=R175"),
+            (Status::BadRequest, "malformed_variant".into())
+        );
+        assert_eq!(
+            get("/svg/Q99999"),
+            (Status::NotFound, "unknown_protein".into())
+        );
+
+        let (status, body) = json(client.get("/proteins/TP53").dispatch());
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["accession"], "P04637");
+        assert_eq!(
+            get("/proteins/Q99999"),
+            (Status::NotFound, "unknown_protein".into())
+        );
+
+        remove("servidor-proteinas");
+    }
+}
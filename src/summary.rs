@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    database::{GeneVariations, IngestError},
+    genomic::Genome,
+    input,
+    isoforms::unversioned,
+};
+
+/// Resumen de las predicciones de una proteína.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// Longitud de la proteína, hasta la última posición con predicciones.
+    pub length: u16,
+    /// Posiciones con predicciones.
+    pub covered: u16,
+    /// Sustituciones con patogenicidad.
+    pub substitutions: usize,
+    /// Media de la patogenicidad de todas las sustituciones.
+    pub mean: Option<f32>,
+    pub median: Option<f32>,
+    /// Fracción de las sustituciones posiblemente patogénicas.
+    pub pathogenic: f32,
+    pub ambiguous: f32,
+    pub benign: f32,
+    /// Media publicada por AlphaMissense en `AlphaMissense_gene_hg38.tsv.gz`, si la
+    /// proteína está en la tabla.
+    pub published_mean: Option<f32>,
+}
+
+impl Summary {
+    /// Calcula el resumen de las predicciones de `gene`, sin la media publicada.
+    pub fn new(gene: &GeneVariations) -> Self {
+        let mut scores = Vec::new();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (_, class) in gene.variations() {
+            if let Some(score) = class.score() {
+                scores.push(score);
+                *counts.entry(class.name()).or_default() += 1;
+            }
+        }
+        scores.sort_unstable_by(f32::total_cmp);
+
+        let total = scores.len();
+        let fraction = |name| match total {
+            0 => 0.0,
+            _ => counts.get(name).copied().unwrap_or(0) as f32 / total as f32,
+        };
+        let median = match total {
+            0 => None,
+            _ if total % 2 == 1 => Some(scores[total / 2]),
+            _ => Some((scores[total / 2 - 1] + scores[total / 2]) / 2.0),
+        };
+
+        Summary {
+            length: gene.len(),
+            covered: (1..=gene.len())
+                .filter(|&position| gene.reference(position).is_some())
+                .count() as u16,
+            substitutions: total,
+            // Se suma en f64 porque una proteína puede tener decenas de miles de
+            // sustituciones.
+            mean: (total > 0)
+                .then(|| (scores.iter().map(|&s| s as f64).sum::<f64>() / total as f64) as f32),
+            median,
+            pathogenic: fraction("pathogenic"),
+            ambiguous: fraction("ambiguous"),
+            benign: fraction("benign"),
+            published_mean: None,
+        }
+    }
+}
+
+/// Medias de patogenicidad publicadas por AlphaMissense para cada transcrito.
+#[derive(Debug, Default)]
+pub struct GeneMeans {
+    /// Media de cada transcrito, sin versión.
+    means: HashMap<String, f32>,
+    /// Transcrito de cada id de uniprot.
+    proteins: HashMap<String, String>,
+}
+
+impl GeneMeans {
+    /// Archivos de entrada que se buscan si no se indica uno, en orden de preferencia.
+    const INPUT: [&'static str; 2] = [
+        "AlphaMissense_gene_hg38.tsv.gz",
+        "AlphaMissense_gene_hg38.tsv",
+    ];
+
+    /// Lee la tabla de medias indicada en la configuración, o la que se encuentre en el
+    /// directorio actual, y relaciona sus transcritos con las proteínas del índice
+    /// genómico. Si no hay tabla, regresa `None`.
+    pub fn open(config: &Config, genome: &Genome) -> Option<Self> {
+        let path = GeneMeans::input(config.gene_means.as_deref())?;
+        let mut means = GeneMeans::load(path).unwrap_or_else(|e| panic!("{}", e));
+        for (uniprot_id, transcript_id) in genome.transcripts() {
+            means.link(uniprot_id, transcript_id);
+        }
+
+        Some(means)
+    }
+
+    /// Regresa `input` o, si no se indica, la primera de las tablas de medias en
+    /// [`GeneMeans::INPUT`] que exista.
+    pub fn input(input: Option<&Path>) -> Option<&Path> {
+        input::find(input, &GeneMeans::INPUT)
+    }
+
+    /// Lee una tabla con las columnas `transcript_id` y `mean_am_pathogenicity`,
+    /// comprimida o no.
+    pub fn load(path: &Path) -> Result<Self, IngestError> {
+        GeneMeans::read(input::decompress(File::open(path)?)?)
+    }
+
+    fn read(file: impl BufRead) -> Result<Self, IngestError> {
+        let mut means = GeneMeans::default();
+        let mut header = true;
+
+        for (number, line) in file.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if std::mem::take(&mut header) && line.starts_with("transcript_id") {
+                continue;
+            }
+
+            let mut values = line.split('\t');
+            let (Some(transcript), Some(mean)) = (values.next(), values.next()) else {
+                return Err(IngestError::MissingColumn {
+                    line: number + 1,
+                    column: 2,
+                });
+            };
+            let mean = mean.trim().parse().map_err(|_| IngestError::BadScore {
+                line: number + 1,
+                column: 2,
+                text: mean.to_owned(),
+            })?;
+            means
+                .means
+                .insert(unversioned(transcript.trim()).to_owned(), mean);
+        }
+
+        Ok(means)
+    }
+
+    /// Relaciona la proteína con id de uniprot `uniprot_id` con su transcrito.
+    pub fn link(&mut self, uniprot_id: &str, transcript_id: &str) {
+        self.proteins
+            .insert(uniprot_id.to_owned(), unversioned(transcript_id).to_owned());
+    }
+
+    /// Media publicada de `id`, que puede ser un transcrito, con o sin versión, o el id de
+    /// uniprot de una proteína relacionada con uno.
+    pub fn mean(&self, id: &str) -> Option<f32> {
+        let transcript = self
+            .proteins
+            .get(id)
+            .map_or(unversioned(id), String::as_str);
+
+        self.means.get(transcript).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{AmClass, Variation};
+
+    #[test]
+    fn summaries_count_classes_and_published_means() {
+        let gene: GeneVariations = [
+            ("M1A", AmClass::Benign(0.1)),
+            ("M1C", AmClass::Ambiguous(0.4)),
+            ("K3A", AmClass::Pathogenic(0.9)),
+            ("K3C", AmClass::Pathogenic(0.8)),
+        ]
+        .into_iter()
+        .map(|(variant, class)| (Variation::from_str(variant).unwrap(), class))
+        .collect();
+
+        let summary = Summary::new(&gene);
+        assert_eq!((summary.length, summary.covered), (3, 2));
+        assert_eq!(summary.substitutions, 4);
+        assert!((summary.mean.unwrap() - 0.55).abs() < 1e-6);
+        assert!((summary.median.unwrap() - 0.6).abs() < 1e-6);
+        assert_eq!(
+            (summary.pathogenic, summary.ambiguous, summary.benign),
+            (0.5, 0.25, 0.25)
+        );
+
+        let mut means = GeneMeans::read(
+            "# Copyright 2023 DeepMind Technologies Limited\n\
+             transcript_id\tmean_am_pathogenicity\n\
+             ENST00000269305.9\t0.7618\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        means.link("P04637", "ENST00000269305.9");
+        assert_eq!(means.mean("P04637"), Some(0.7618));
+        assert_eq!(means.mean("ENST00000269305"), Some(0.7618));
+        assert_eq!(means.mean("P00000"), None);
+    }
+}
@@ -0,0 +1,331 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    heatmap::Heatmap,
+    variations::{Cutoffs, Variation},
+};
+
+/// Número de filas a las que conviene reducir una matriz antes de dibujarla, para que el
+/// SVG de proteínas largas, como la titina, no pese decenas de megabytes.
+pub const MAX_ROWS: usize = 2000;
+
+/// Ancho máximo del área de las gráficas, en pixeles.
+const WIDTH: f32 = 1200.0;
+/// Ancho máximo de una posición.
+const CELL_WIDTH: f32 = 12.0;
+/// Alto de un aminoácido en el mapa de calor.
+const CELL_HEIGHT: f32 = 10.0;
+const PROFILE_HEIGHT: f32 = 150.0;
+/// Márgenes a la izquierda, para las etiquetas, y arriba, para el título.
+const LEFT: f32 = 40.0;
+const TOP: f32 = 30.0;
+/// Espacio debajo de cada gráfica para su eje.
+const AXIS: f32 = 30.0;
+
+/// Gráficas que se pueden dibujar.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Plot {
+    /// Posiciones por aminoácidos, coloreadas por patogenicidad.
+    Heatmap,
+    /// Patogenicidad media por posición.
+    Profile,
+    /// Las dos, una sobre otra.
+    Figure,
+}
+
+impl std::fmt::Display for Plot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Plot::Heatmap => write!(f, "heatmap"),
+            Plot::Profile => write!(f, "profile"),
+            Plot::Figure => write!(f, "figure"),
+        }
+    }
+}
+
+impl std::str::FromStr for Plot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "heatmap" => Ok(Plot::Heatmap),
+            "profile" => Ok(Plot::Profile),
+            "figure" => Ok(Plot::Figure),
+            _ => Err(format!(
+                "Gráfica desconocida: {:?} (se admiten heatmap, profile y figure)",
+                s
+            )),
+        }
+    }
+}
+
+/// Dibuja `heatmap` como SVG, con el título `title` y un marcador por cada variación de
+/// `markers` dentro del intervalo de la matriz.
+pub fn render(heatmap: &Heatmap, plot: Plot, title: &str, markers: &[Variation]) -> String {
+    let canvas = Canvas::new(heatmap, markers);
+    let mut body = String::new();
+    let mut top = TOP;
+
+    if plot != Plot::Profile {
+        canvas.heatmap(&mut body, top);
+        top += 20.0 * CELL_HEIGHT + AXIS;
+    }
+    if plot != Plot::Heatmap {
+        canvas.profile(&mut body, top + 10.0);
+        top += 10.0 + PROFILE_HEIGHT + AXIS;
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n\
+         <text x=\"{LEFT}\" y=\"18\" font-size=\"14\">{}</text>\n{}</svg>\n",
+        escape(title),
+        body,
+        w = LEFT + canvas.width + 20.0,
+        h = top,
+    )
+}
+
+/// Escala de colores de AlphaMissense: azul para benigno, blanco en el medio y rojo para
+/// patogénico.
+fn color(score: f32) -> String {
+    const BENIGN: [f32; 3] = [49.0, 54.0, 149.0];
+    const MIDDLE: [f32; 3] = [247.0, 247.0, 247.0];
+    const PATHOGENIC: [f32; 3] = [165.0, 0.0, 38.0];
+
+    let score = score.clamp(0.0, 1.0);
+    let (from, to, t) = match score < 0.5 {
+        true => (BENIGN, MIDDLE, score * 2.0),
+        false => (MIDDLE, PATHOGENIC, score * 2.0 - 1.0),
+    };
+    let channel = |i: usize| (from[i] + (to[i] - from[i]) * t).round() as u8;
+
+    format!("#{:02x}{:02x}{:02x}", channel(0), channel(1), channel(2))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Posiciones de los elementos de las gráficas.
+struct Canvas<'a> {
+    heatmap: &'a Heatmap,
+    markers: Vec<&'a Variation>,
+    /// Ancho de cada fila de la matriz.
+    cell: f32,
+    width: f32,
+}
+
+impl<'a> Canvas<'a> {
+    fn new(heatmap: &'a Heatmap, markers: &'a [Variation]) -> Self {
+        let rows = heatmap.scores.len().max(1) as f32;
+        let cell = (WIDTH / rows).min(CELL_WIDTH);
+
+        Canvas {
+            heatmap,
+            markers: markers
+                .iter()
+                .filter(|v| (heatmap.start..=heatmap.end).contains(&v.position))
+                .collect(),
+            cell,
+            width: cell * rows,
+        }
+    }
+
+    /// Coordenada horizontal del centro de `position`.
+    fn x(&self, position: u16) -> f32 {
+        let offset = (position - self.heatmap.start) as f32 + 0.5;
+
+        LEFT + offset / self.heatmap.step as f32 * self.cell
+    }
+
+    fn heatmap(&self, out: &mut String, top: f32) {
+        let height = 20.0 * CELL_HEIGHT;
+        let _ = writeln!(
+            out,
+            "<g shape-rendering=\"crispEdges\">\n<rect x=\"{LEFT}\" y=\"{top}\" width=\"{}\" \
+             height=\"{height}\" fill=\"#e0e0e0\"/>",
+            self.width
+        );
+        for (row, scores) in self.heatmap.scores.iter().enumerate() {
+            let x = LEFT + row as f32 * self.cell;
+            for (column, score) in scores.iter().enumerate() {
+                if let Some(score) = score {
+                    let _ = writeln!(
+                        out,
+                        "<rect x=\"{x:.2}\" y=\"{}\" width=\"{:.2}\" height=\"{CELL_HEIGHT}\" \
+                         fill=\"{}\"/>",
+                        top + column as f32 * CELL_HEIGHT,
+                        self.cell,
+                        color(*score)
+                    );
+                }
+            }
+        }
+        out.push_str("</g>\n");
+
+        for (column, letter) in self.heatmap.columns.chars().enumerate() {
+            let _ = writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" font-size=\"8\" text-anchor=\"end\">{}</text>",
+                LEFT - 4.0,
+                top + (column as f32 + 0.8) * CELL_HEIGHT,
+                letter
+            );
+        }
+
+        for variation in &self.markers {
+            let column = variation.variant.index();
+            let width = self.cell.max(3.0);
+            let _ = writeln!(
+                out,
+                "<rect x=\"{:.2}\" y=\"{}\" width=\"{width:.2}\" height=\"{CELL_HEIGHT}\" \
+                 fill=\"none\" stroke=\"black\" stroke-width=\"1.5\"><title>{}</title></rect>",
+                self.x(variation.position) - width / 2.0,
+                top + column as f32 * CELL_HEIGHT,
+                variation
+            );
+        }
+
+        self.axis(out, top + height);
+    }
+
+    fn profile(&self, out: &mut String, top: f32) {
+        let y = |score: f32| top + (1.0 - score) * PROFILE_HEIGHT;
+        let _ = writeln!(
+            out,
+            "<rect x=\"{LEFT}\" y=\"{top}\" width=\"{}\" height=\"{PROFILE_HEIGHT}\" \
+             fill=\"none\" stroke=\"#999\"/>",
+            self.width
+        );
+        for score in [0.0, 0.5, 1.0] {
+            let _ = writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" font-size=\"8\" text-anchor=\"end\">{}</text>",
+                LEFT - 4.0,
+                y(score) + 3.0,
+                score
+            );
+        }
+
+        let cutoffs = Cutoffs::default();
+        for (cutoff, score) in [(cutoffs.benign, 0.0), (cutoffs.pathogenic, 1.0)] {
+            let _ = writeln!(
+                out,
+                "<line x1=\"{LEFT}\" y1=\"{y:.2}\" x2=\"{}\" y2=\"{y:.2}\" stroke=\"{}\" \
+                 stroke-dasharray=\"4 3\"/>",
+                LEFT + self.width,
+                color(score),
+                y = y(cutoff)
+            );
+        }
+
+        // Media de cada fila; las filas sin predicciones cortan la línea.
+        let means = self.heatmap.scores.iter().map(|scores| {
+            let scores: Vec<f32> = scores.iter().flatten().copied().collect();
+            (!scores.is_empty()).then(|| scores.iter().sum::<f32>() / scores.len() as f32)
+        });
+        let mut segments: Vec<Vec<String>> = vec![Vec::new()];
+        for (row, mean) in means.enumerate() {
+            match mean {
+                Some(mean) => segments.last_mut().unwrap().push(format!(
+                    "{:.2},{:.2}",
+                    LEFT + (row as f32 + 0.5) * self.cell,
+                    y(mean)
+                )),
+                None => segments.push(Vec::new()),
+            }
+        }
+        for segment in segments.iter().filter(|segment| !segment.is_empty()) {
+            let _ = writeln!(
+                out,
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>",
+                segment.join(" ")
+            );
+        }
+
+        for variation in &self.markers {
+            let x = self.x(variation.position);
+            let _ = writeln!(
+                out,
+                "<line x1=\"{x:.2}\" y1=\"{top}\" x2=\"{x:.2}\" y2=\"{}\" stroke=\"#d95f02\"/>\n\
+                 <text x=\"{x:.2}\" y=\"{}\" font-size=\"8\" text-anchor=\"middle\" \
+                 fill=\"#d95f02\">{}</text>",
+                top + PROFILE_HEIGHT,
+                top - 2.0,
+                variation
+            );
+        }
+
+        self.axis(out, top + PROFILE_HEIGHT);
+    }
+
+    /// Eje de posiciones, con marcas en múltiplos redondos.
+    fn axis(&self, out: &mut String, top: f32) {
+        let length = (self.heatmap.end - self.heatmap.start) as u32 + 1;
+        let tick = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000, 10000]
+            .into_iter()
+            .find(|tick| length / tick <= 10)
+            .unwrap_or(20000);
+
+        let first = self.heatmap.start as u32;
+        let first = first.div_ceil(tick) * tick;
+        for position in (first..=self.heatmap.end as u32).step_by(tick as usize) {
+            let x = self.x(position as u16);
+            let _ = writeln!(
+                out,
+                "<line x1=\"{x:.2}\" y1=\"{top}\" x2=\"{x:.2}\" y2=\"{}\" stroke=\"black\"/>\n\
+                 <text x=\"{x:.2}\" y=\"{}\" font-size=\"9\" text-anchor=\"middle\">{}</text>",
+                top + 4.0,
+                top + 14.0,
+                position
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{database::GeneVariations, heatmap::HeatmapOptions, AmClass};
+
+    #[test]
+    fn renders_heatmaps_profiles_and_markers() {
+        let gene: GeneVariations = [
+            ("M1R", AmClass::Benign(0.0)),
+            ("K2R", AmClass::Pathogenic(1.0)),
+            ("L4A", AmClass::Ambiguous(0.5)),
+        ]
+        .into_iter()
+        .map(|(variant, class)| (Variation::from_str(variant).unwrap(), class))
+        .collect();
+        let heatmap = Heatmap::new(&gene, &HeatmapOptions::default()).unwrap();
+        let markers = [
+            Variation::from_str("K2R").unwrap(),
+            Variation::from_str("A90V").unwrap(),
+        ];
+
+        let svg = render(&heatmap, Plot::Figure, "P<1>", &markers);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("P&lt;1&gt;"));
+        assert!(svg.contains("fill=\"#313695\""));
+        assert!(svg.contains("fill=\"#a50026\""));
+        assert!(svg.contains("<title>K2R</title>"));
+        assert!(!svg.contains("A90V"));
+        // La posición 3 no tiene predicciones y corta la línea del perfil.
+        assert_eq!(svg.matches("<polyline").count(), 2);
+
+        let heatmap_only = render(&heatmap, Plot::Heatmap, "P1", &[]);
+        assert!(!heatmap_only.contains("<polyline"));
+        assert_eq!(Plot::from_str("Profile"), Ok(Plot::Profile));
+    }
+}
@@ -1,29 +1,413 @@
-extern crate simd_json;
-
-use simd_json::prelude::*;
-
-/// Dada una id de uniprot, consulta a uniprot para conseguir su nombre.
-pub fn _get_gene_name(uniprot_id: &str) -> String {
-    let url = format!("https://www.uniprot.org/uniprot/{}.json", uniprot_id);
-    match reqwest::blocking::get(&url) {
-        Ok(re) => {
-            match re.text() {
-                Ok(mut txt) => {
-                    let bytes = unsafe {txt.as_bytes_mut()};
-                    let parsed_json = simd_json::to_borrowed_value(bytes).expect("Error fatal, no es un json.");
-                    let genes = parsed_json.get("genes")
-                        .and_then(|g| g.get_idx(0))
-                        .and_then(|g| g.get("geneName"))
-                        .and_then(|g| g.get("value"))
-                        .unwrap_or_else(|| {
-                            &parsed_json.get("uniProtkbId").expect("JSon con formato inválido. Es imposible determinar un nombre")
-                        });
-                    
-                    genes.as_str().expect(&url).to_owned()
-                },
-                Err(_) => todo!(),
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use serde_json::Value;
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::proteins::Entry;
+
+/// Dirección de la API REST de UniProt.
+pub const BASE_URL: &str = "https://rest.uniprot.org";
+
+/// Opciones de [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Dirección de la API. Se cambia para usar un espejo o un servidor de pruebas.
+    pub base_url: String,
+    /// Reintentos cuando no se puede conectar o UniProt responde 429 o 5xx.
+    pub retries: u32,
+    /// Espera antes del primer reintento; se duplica en cada uno. Si UniProt indica
+    /// `Retry-After`, se espera lo que indique.
+    pub backoff: Duration,
+    /// Tiempo mínimo entre el inicio de dos peticiones.
+    pub interval: Duration,
+    /// Tiempo máximo de cada petición.
+    pub timeout: Duration,
+    /// Directorio donde se guardan las respuestas, para no volver a pedirlas. Sin él no
+    /// se guardan.
+    pub cache: Option<PathBuf>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        ClientOptions {
+            base_url: BASE_URL.to_owned(),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            interval: Duration::from_millis(100),
+            timeout: Duration::from_secs(30),
+            cache: None,
+        }
+    }
+}
+
+/// Errores al consultar a UniProt.
+#[derive(Debug)]
+pub enum UniprotError {
+    /// El id no tiene la forma de un id de uniprot, como `P04637` o `P04637-2`.
+    InvalidAccession {
+        accession: String,
+    },
+    NotFound {
+        accession: String,
+    },
+    /// La entrada existió pero ya no está en UniProtKB.
+    Inactive {
+        accession: String,
+    },
+    /// UniProt respondió con un error, después de los reintentos si aplican.
+    Status {
+        accession: String,
+        status: u16,
+    },
+    Http(reqwest::Error),
+    Json {
+        accession: String,
+        error: serde_json::Error,
+    },
+    /// La respuesta es JSON, pero no una entrada de UniProtKB con nombre.
+    Malformed {
+        accession: String,
+    },
+    Cache(io::Error),
+}
+
+impl std::fmt::Display for UniprotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UniprotError::InvalidAccession { accession } => {
+                write!(f, "{:?} no es un id de uniprot", accession)
+            }
+            UniprotError::NotFound { accession } => {
+                write!(f, "UniProt no tiene la entrada {}", accession)
+            }
+            UniprotError::Inactive { accession } => {
+                write!(f, "La entrada {} ya no está en UniProtKB", accession)
+            }
+            UniprotError::Status { accession, status } => {
+                write!(f, "UniProt respondió {} al pedir {}", status, accession)
+            }
+            UniprotError::Http(e) => write!(f, "No se pudo consultar a UniProt: {}", e),
+            UniprotError::Json { accession, error } => {
+                write!(
+                    f,
+                    "La respuesta de UniProt para {} no es JSON: {}",
+                    accession, error
+                )
             }
-        },
-        Err(_) => todo!(),
-    } 
-}
\ No newline at end of file
+            UniprotError::Malformed { accession } => write!(
+                f,
+                "La respuesta de UniProt para {} no es una entrada de UniProtKB",
+                accession
+            ),
+            UniprotError::Cache(e) => write!(f, "Error en el caché de UniProt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for UniprotError {}
+
+/// Cliente de la API REST de UniProt, con reintentos, un límite de peticiones por
+/// segundo y, si se indica, un caché en disco.
+pub struct Client {
+    http: reqwest::Client,
+    options: ClientOptions,
+    /// Momento a partir del cual se puede iniciar la siguiente petición.
+    next: Mutex<Instant>,
+}
+
+impl Client {
+    pub fn new(options: ClientOptions) -> Result<Self, UniprotError> {
+        let http = reqwest::Client::builder()
+            .timeout(options.timeout)
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .map_err(UniprotError::Http)?;
+
+        Ok(Client {
+            http,
+            options,
+            next: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Entrada de UniProtKB de la proteína `accession`.
+    pub async fn entry(&self, accession: &str) -> Result<Entry, UniprotError> {
+        let json = self.json(accession).await?;
+
+        Entry::from_json(&json).ok_or_else(|| UniprotError::Malformed {
+            accession: accession.to_owned(),
+        })
+    }
+
+    /// Nombre del gen de la proteína `accession` o, si no tiene, el nombre de su entrada,
+    /// como `P53_HUMAN`.
+    pub async fn gene_name(&self, accession: &str) -> Result<String, UniprotError> {
+        let json = self.json(accession).await?;
+
+        json.get("genes")
+            .and_then(|genes| genes.get(0))
+            .and_then(|gene| gene.get("geneName"))
+            .and_then(|name| name.get("value"))
+            .or_else(|| json.get("uniProtkbId"))
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| UniprotError::Malformed {
+                accession: accession.to_owned(),
+            })
+    }
+
+    /// JSON de la entrada, del caché o de la API. Solo se guardan en el caché las
+    /// respuestas que son entradas activas.
+    async fn json(&self, accession: &str) -> Result<Value, UniprotError> {
+        let valid = !accession.is_empty()
+            && accession
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err(UniprotError::InvalidAccession {
+                accession: accession.to_owned(),
+            });
+        }
+
+        let cache = self
+            .options
+            .cache
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", accession)));
+        let cached = match &cache {
+            Some(path) => match tokio::fs::read_to_string(path).await {
+                Ok(text) => Some(text),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(UniprotError::Cache(e)),
+            },
+            None => None,
+        };
+
+        let fetched = cached.is_none();
+        let text = match cached {
+            Some(text) => text,
+            None => self.fetch(accession).await?,
+        };
+        let json: Value = serde_json::from_str(&text).map_err(|error| UniprotError::Json {
+            accession: accession.to_owned(),
+            error,
+        })?;
+
+        let inactive = json
+            .get("entryType")
+            .and_then(Value::as_str)
+            .is_some_and(|kind| kind.starts_with("Inactive"));
+        if inactive {
+            return Err(UniprotError::Inactive {
+                accession: accession.to_owned(),
+            });
+        }
+
+        if let (Some(path), true) = (&cache, fetched) {
+            let temporary = path.with_extension("tmp");
+            let write = async {
+                tokio::fs::create_dir_all(path.parent().unwrap_or(path)).await?;
+                tokio::fs::write(&temporary, &text).await?;
+                tokio::fs::rename(&temporary, path).await
+            };
+            write.await.map_err(UniprotError::Cache)?;
+        }
+
+        Ok(json)
+    }
+
+    /// Pide la entrada a la API, reintentando si falla la conexión o UniProt está saturado.
+    async fn fetch(&self, accession: &str) -> Result<String, UniprotError> {
+        let url = format!(
+            "{}/uniprotkb/{}.json",
+            self.options.base_url.trim_end_matches('/'),
+            accession
+        );
+        let status_error = |status: StatusCode| UniprotError::Status {
+            accession: accession.to_owned(),
+            status: status.as_u16(),
+        };
+
+        let mut attempt = 0;
+        loop {
+            self.wait_turn().await;
+
+            let retry_after = match self.http.get(&url).send().await {
+                Ok(response) if response.status().is_success() => {
+                    return response.text().await.map_err(UniprotError::Http);
+                }
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                    return Err(UniprotError::NotFound {
+                        accession: accession.to_owned(),
+                    });
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let retry = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+                    if !retry || attempt == self.options.retries {
+                        return Err(status_error(status));
+                    }
+
+                    response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+                        .map(Duration::from_secs)
+                }
+                Err(e) if (e.is_connect() || e.is_timeout()) && attempt < self.options.retries => {
+                    None
+                }
+                Err(e) => return Err(UniprotError::Http(e)),
+            };
+
+            let backoff = self.options.backoff * 2u32.saturating_pow(attempt);
+            tokio::time::sleep(retry_after.unwrap_or(backoff)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Espera a que pase `interval` desde el inicio de la petición anterior.
+    async fn wait_turn(&self) {
+        let mut next = self.next.lock().await;
+        tokio::time::sleep_until(*next).await;
+        *next = Instant::now() + self.options.interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const TP53: &str = r#"{"primaryAccession": "P04637", "uniProtkbId": "P53_HUMAN",
+        "genes": [{"geneName": {"value": "TP53"}}], "sequence": {"value": "MEEP"}}"#;
+
+    fn options(server: &MockServer) -> ClientOptions {
+        ClientOptions {
+            base_url: server.uri(),
+            backoff: Duration::from_millis(1),
+            interval: Duration::ZERO,
+            ..ClientOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_the_entries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/uniprotkb/P04637.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(TP53))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let cache = std::env::temp_dir().join(format!("uniprot-cache-{}", std::process::id()));
+        let client = Client::new(ClientOptions {
+            cache: Some(cache.clone()),
+            ..options(&server)
+        })
+        .unwrap();
+        assert_eq!(client.gene_name("P04637").await.unwrap(), "TP53");
+        let entry = client.entry("P04637").await.unwrap();
+        assert_eq!(entry.sequence, "MEEP");
+        assert!(cache.join("P04637.json").exists());
+
+        std::fs::remove_dir_all(cache).unwrap();
+    }
+
+    #[tokio::test]
+    async fn retries_until_uniprot_answers() {
+        let server = MockServer::start().await;
+        Mock::given(path("/uniprotkb/P04637.json"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(path("/uniprotkb/P04637.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(TP53))
+            .mount(&server)
+            .await;
+        Mock::given(path("/uniprotkb/Q00000.json"))
+            .respond_with(ResponseTemplate::new(429))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(ClientOptions {
+            retries: 2,
+            ..options(&server)
+        })
+        .unwrap();
+        assert_eq!(client.gene_name("P04637").await.unwrap(), "TP53");
+        // La petición y dos reintentos.
+        assert!(matches!(
+            client.gene_name("Q00000").await,
+            Err(UniprotError::Status { status: 429, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_typed_errors() {
+        let server = MockServer::start().await;
+        Mock::given(path("/uniprotkb/P00000.json"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(path("/uniprotkb/P11111.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"primaryAccession": "P11111", "entryType": "Inactive"}"#),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(path("/uniprotkb/P22222.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/uniprotkb/P33333.json"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(ClientOptions {
+            interval: Duration::from_millis(50),
+            ..options(&server)
+        })
+        .unwrap();
+        let start = std::time::Instant::now();
+        let name = |accession| client.gene_name(accession);
+        assert!(matches!(
+            name("P00000").await,
+            Err(UniprotError::NotFound { .. })
+        ));
+        assert!(matches!(
+            name("P11111").await,
+            Err(UniprotError::Inactive { .. })
+        ));
+        assert!(matches!(
+            name("P22222").await,
+            Err(UniprotError::Json { .. })
+        ));
+        assert!(matches!(
+            name("P33333").await,
+            Err(UniprotError::Status { status: 400, .. })
+        ));
+        assert!(matches!(
+            name("../P04637").await,
+            Err(UniprotError::InvalidAccession { .. })
+        ));
+        // Cuatro peticiones, con al menos 50 ms entre cada una.
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
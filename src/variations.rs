@@ -1,57 +1,390 @@
 use std::str::FromStr;
 
-use serde::{Serialize, Deserialize};
+use serde::{Deserialize, Serialize};
 
 use crate::aminoacids::AminoAcid;
 
 /// Clasificación de la variante de la proteína
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum AmClass {
     Benign(f32),
     Pathogenic(f32),
     Ambiguous(f32),
-    Undefined
+    Undefined,
 }
 
-impl ToString for AmClass {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for AmClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AmClass::Benign(w) => format!("Posiblemente benigno: {}", w),
-            AmClass::Pathogenic(w) => format!("Posiblemente patógenico: {}", w),
-            AmClass::Ambiguous(w) => format!("Ambiguo: {}", w),
-            AmClass::Undefined => "No Definido".to_owned(),
+            AmClass::Benign(w) => write!(f, "Posiblemente benigno: {}", w),
+            AmClass::Pathogenic(w) => write!(f, "Posiblemente patógenico: {}", w),
+            AmClass::Ambiguous(w) => write!(f, "Ambiguo: {}", w),
+            AmClass::Undefined => write!(f, "No Definido"),
+        }
+    }
+}
+
+impl AmClass {
+    /// Nombre de la clase, tal como aparece en las tablas de AlphaMissense.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmClass::Benign(_) => "benign",
+            AmClass::Pathogenic(_) => "pathogenic",
+            AmClass::Ambiguous(_) => "ambiguous",
+            AmClass::Undefined => "undefined",
+        }
+    }
+
+    /// Patogenicidad de la variante, si está definida.
+    pub fn score(&self) -> Option<f32> {
+        match self {
+            AmClass::Benign(w) | AmClass::Pathogenic(w) | AmClass::Ambiguous(w) => Some(*w),
+            AmClass::Undefined => None,
+        }
+    }
+}
+
+/// Puntos de corte con los que se clasifica una patogenicidad.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Cutoffs {
+    /// Por debajo de este valor la variante es posiblemente benigna.
+    pub benign: f32,
+    /// Por encima de este valor la variante es posiblemente patogénica.
+    pub pathogenic: f32,
+}
+
+impl Default for Cutoffs {
+    /// Los puntos de corte publicados con AlphaMissense.
+    fn default() -> Self {
+        Cutoffs {
+            benign: 0.34,
+            pathogenic: 0.564,
+        }
+    }
+}
+
+impl Cutoffs {
+    /// Clasifica una patogenicidad según estos puntos de corte.
+    pub fn classify(&self, pathogenicity: f32) -> AmClass {
+        if pathogenicity < self.benign {
+            AmClass::Benign(pathogenicity)
+        } else if pathogenicity > self.pathogenic {
+            AmClass::Pathogenic(pathogenicity)
+        } else {
+            AmClass::Ambiguous(pathogenicity)
         }
     }
 }
 
 /// Representación de una variación
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub struct Variation {
     /// Base del aminoacido.
     pub base: AminoAcid,
     /// Posicion de la variación.
     pub position: u16,
-    /// Aminoacido modificado.
+    /// Aminoacido modificado. Si es igual a la base, la variación es sinónima.
     pub variant: AminoAcid,
 }
 
+impl Variation {
+    /// Indica si la variación no cambia el aminoácido.
+    pub fn is_synonymous(&self) -> bool {
+        self.base == self.variant
+    }
+}
+
+impl std::fmt::Display for Variation {
+    /// Escribe la variación con códigos de una letra, como `R175H`, o en notación HGVS
+    /// con el formato alterno (`{:#}`), como `p.Arg175His` o `p.Arg175=`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "p.{}{}", AminoAcid::HGVS_CODE[self.base], self.position)?;
+            match self.is_synonymous() {
+                true => write!(f, "="),
+                false => write!(f, "{}", AminoAcid::HGVS_CODE[self.variant]),
+            }
+        } else {
+            write!(
+                f,
+                "{}{}{}",
+                AminoAcid::SINGLE_LETTER_CODE[self.base],
+                self.position,
+                AminoAcid::SINGLE_LETTER_CODE[self.variant]
+            )
+        }
+    }
+}
+
+/// Cambio de un aminoácido en una proteína.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProteinChange {
+    /// Sustitución por otro aminoácido, o por el mismo si es sinónima.
+    Substitution(Variation),
+    /// Sustitución por un codón de paro, como `p.Arg175Ter` o `R175*`. AlphaMissense no
+    /// las evalúa.
+    Nonsense { base: AminoAcid, position: u16 },
+}
+
+impl std::fmt::Display for ProteinChange {
+    /// Igual que [`Variation`]: `R175*` o, con el formato alterno, `p.Arg175Ter`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProteinChange::Substitution(variation) => variation.fmt(f),
+            ProteinChange::Nonsense { base, position } if f.alternate() => {
+                write!(f, "p.{}{}Ter", AminoAcid::HGVS_CODE[*base], position)
+            }
+            ProteinChange::Nonsense { base, position } => {
+                write!(f, "{}{}*", AminoAcid::SINGLE_LETTER_CODE[*base], position)
+            }
+        }
+    }
+}
+
+/// Errores al interpretar una variación.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariationError {
+    /// El texto no tiene la forma de una variación.
+    Malformed(String),
+    /// El código no corresponde a ningún aminoácido.
+    UnknownAminoAcid(String),
+    /// La posición no es un número entre 1 y 65535.
+    BadPosition(String),
+    /// Variación válida en HGVS, pero de un tipo que no es una sustitución de un solo
+    /// aminoácido, como deleciones, inserciones o corrimientos del marco de lectura.
+    Unsupported { kind: &'static str },
+    /// Variación sin sentido: [`Variation`] solo representa sustituciones.
+    Nonsense,
+}
+
+impl std::fmt::Display for VariationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariationError::Malformed(s) => write!(f, "{:?} no es una variación válida", s),
+            VariationError::UnknownAminoAcid(s) => write!(f, "Aminoácido desconocido: {:?}", s),
+            VariationError::BadPosition(s) => write!(f, "Posición inválida: {:?}", s),
+            VariationError::Unsupported { kind } => {
+                write!(
+                    f,
+                    "Solo se admiten sustituciones de un aminoácido, no {}",
+                    kind
+                )
+            }
+            VariationError::Nonsense => {
+                write!(f, "AlphaMissense no evalúa variaciones sin sentido")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VariationError {}
+
+/// Tipos de variación de HGVS que no son sustituciones, por la palabra que los identifica.
+const UNSUPPORTED: [(&str, &str); 6] = [
+    ("delins", "deleciones-inserciones"),
+    ("del", "deleciones"),
+    ("ins", "inserciones"),
+    ("dup", "duplicaciones"),
+    ("fs", "corrimientos del marco de lectura"),
+    ("ext", "extensiones"),
+];
+
+impl FromStr for ProteinChange {
+    type Err = VariationError;
+
+    /// Acepta la forma compacta (`R175H`) y la notación de proteína de HGVS
+    /// (`p.Arg175His`, `p.(Arg175His)`, `p.Arg175=`, `p.Arg175Ter`, `p.R175*`), con
+    /// códigos de una o tres letras y sin distinguir mayúsculas de minúsculas.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || VariationError::Malformed(s.to_owned());
+
+        let mut rest = s.trim();
+        if let Some(hgvs) = rest.strip_prefix("p.").or_else(|| rest.strip_prefix("P.")) {
+            rest = hgvs;
+        }
+        // Los paréntesis indican que el cambio se predijo, no que se observó.
+        if let Some(predicted) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+            rest = predicted;
+        }
+
+        if matches!(rest, "0" | "?" | "0?") {
+            return Err(VariationError::Unsupported {
+                kind: "efectos desconocidos o sin producto",
+            });
+        }
+        if rest.contains(['_', '[', ';']) {
+            return Err(VariationError::Unsupported {
+                kind: "cambios de varios aminoácidos",
+            });
+        }
+
+        let digits = rest
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(malformed)?;
+        let (base, tail) = rest.split_at(digits);
+        let end = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (position, variant) = tail.split_at(end);
+
+        let lowercase = variant.to_ascii_lowercase();
+        if let Some((_, kind)) = UNSUPPORTED
+            .iter()
+            .find(|(word, _)| lowercase.contains(word))
+        {
+            return Err(VariationError::Unsupported { kind });
+        }
+        if matches!(base, "*") || base.eq_ignore_ascii_case("ter") {
+            return Err(VariationError::Unsupported {
+                kind: "pérdidas del codón de paro",
+            });
+        }
+
+        let base = AminoAcid::from_str(base)
+            .map_err(|_| VariationError::UnknownAminoAcid(base.to_owned()))?;
+        let position = position
+            .parse::<u16>()
+            .ok()
+            .filter(|&p| p > 0)
+            .ok_or_else(|| VariationError::BadPosition(position.to_owned()))?;
+
+        match variant {
+            "" => Err(malformed()),
+            "=" => Ok(ProteinChange::Substitution(Variation {
+                base,
+                position,
+                variant: base,
+            })),
+            "*" | "X" | "x" => Ok(ProteinChange::Nonsense { base, position }),
+            v if v.eq_ignore_ascii_case("ter") => Ok(ProteinChange::Nonsense { base, position }),
+            v => Ok(ProteinChange::Substitution(Variation {
+                base,
+                position,
+                variant: AminoAcid::from_str(v)
+                    .map_err(|_| VariationError::UnknownAminoAcid(v.to_owned()))?,
+            })),
+        }
+    }
+}
+
 impl FromStr for Variation {
-    type Err = ();
+    type Err = VariationError;
 
+    /// Acepta las mismas formas que [`ProteinChange`], excepto las variaciones sin sentido.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() < 3 {
-            return Err(());
-        } 
+        // Atajo para la forma compacta, que es la de las tablas de AlphaMissense.
+        let bytes = s.as_bytes();
+        if let [base, digits @ .., variant] = bytes {
+            if base.is_ascii_uppercase()
+                && variant.is_ascii_uppercase()
+                && !digits.is_empty()
+                && digits.iter().all(u8::is_ascii_digit)
+            {
+                let base = AminoAcid::from_str(&s[..1]);
+                let position = s[1..s.len() - 1].parse::<u16>().ok().filter(|&p| p > 0);
+                let variant = AminoAcid::from_str(&s[s.len() - 1..]);
+
+                if let (Ok(base), Some(position), Ok(variant)) = (base, position, variant) {
+                    return Ok(Variation {
+                        base,
+                        position,
+                        variant,
+                    });
+                }
+            }
+        }
+
+        match ProteinChange::from_str(s)? {
+            ProteinChange::Substitution(variation) => Ok(variation),
+            ProteinChange::Nonsense { .. } => Err(VariationError::Nonsense),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let i = s.len() - 1;
+    #[test]
+    fn parses_compact_and_hgvs_notation() {
+        let r175h = Variation {
+            base: AminoAcid::Arginine,
+            position: 175,
+            variant: AminoAcid::Histidine,
+        };
+        for s in [
+            "R175H",
+            "r175h",
+            "p.R175H",
+            "p.Arg175His",
+            "p.(Arg175His)",
+            "ARG175HIS",
+            "p.arg175his",
+            " p.Arg175His ",
+        ] {
+            assert_eq!(Variation::from_str(s), Ok(r175h), "{}", s);
+        }
 
-        let base = AminoAcid::from_str(&s[0..1])?;
-        let position = s[1..i].parse::<u16>().map_err(|_| ())?;
-        let variant = AminoAcid::from_str(&s[i..])?;
+        let synonymous = Variation::from_str("p.Arg175=").unwrap();
+        assert!(synonymous.is_synonymous());
+        assert_eq!(format!("{:#}", synonymous), "p.Arg175=");
+
+        for s in ["p.Arg175Ter", "p.(Arg175*)", "R175*", "R175X"] {
+            assert_eq!(
+                ProteinChange::from_str(s),
+                Ok(ProteinChange::Nonsense {
+                    base: AminoAcid::Arginine,
+                    position: 175
+                }),
+                "{}",
+                s
+            );
+            assert_eq!(Variation::from_str(s), Err(VariationError::Nonsense));
+        }
+
+        assert_eq!(r175h.to_string(), "R175H");
+        assert_eq!(format!("{:#}", r175h), "p.Arg175His");
+        assert_eq!(
+            format!("{:#}", ProteinChange::from_str("R175*").unwrap()),
+            "p.Arg175Ter"
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_and_malformed_variations() {
+        for (s, kind) in [
+            ("p.Val7del", "deleciones"),
+            ("p.Lys23_Val25del", "cambios de varios aminoácidos"),
+            ("p.Cys28delinsTrpVal", "deleciones-inserciones"),
+            ("p.Arg97ProfsTer23", "corrimientos del marco de lectura"),
+            ("p.Met1ext-5", "extensiones"),
+            ("p.Ter110GlnextTer17", "extensiones"),
+            ("p.Ter110Gln", "pérdidas del codón de paro"),
+            ("p.0", "efectos desconocidos o sin producto"),
+        ] {
+            assert_eq!(
+                Variation::from_str(s),
+                Err(VariationError::Unsupported { kind }),
+                "{}",
+                s
+            );
+        }
 
-        Ok(Variation {
-            base,
-            position,
-            variant,
-        })
+        assert_eq!(
+            Variation::from_str("B175H"),
+            Err(VariationError::UnknownAminoAcid("B".to_owned()))
+        );
+        assert_eq!(
+            Variation::from_str("R0H"),
+            Err(VariationError::BadPosition("0".to_owned()))
+        );
+        assert_eq!(
+            Variation::from_str("R175"),
+            Err(VariationError::Malformed("R175".to_owned()))
+        );
+        assert_eq!(
+            Variation::from_str("RH"),
+            Err(VariationError::Malformed("RH".to_owned()))
+        );
     }
 }
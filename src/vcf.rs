@@ -0,0 +1,378 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{
+    genomic::{Annotation, Assembly, Genome, GenomicError},
+    input,
+};
+
+/// Campos INFO que se agregan a cada variante, con la línea que los describe en el
+/// encabezado. Cada uno tiene un valor por alelo alternativo; si un alelo afecta a varias
+/// proteínas, sus valores se separan con `|`.
+const FIELDS: [(&str, &str); 4] = [
+    (
+        "AM_SCORE",
+        "##INFO=<ID=AM_SCORE,Number=A,Type=String,Description=\"AlphaMissense pathogenicity score\">",
+    ),
+    (
+        "AM_CLASS",
+        "##INFO=<ID=AM_CLASS,Number=A,Type=String,Description=\"AlphaMissense classification: benign, ambiguous or pathogenic\">",
+    ),
+    (
+        "AM_PROTEIN_VARIANT",
+        "##INFO=<ID=AM_PROTEIN_VARIANT,Number=A,Type=String,Description=\"Amino acid substitution scored by AlphaMissense\">",
+    ),
+    (
+        "AM_UNIPROT",
+        "##INFO=<ID=AM_UNIPROT,Number=A,Type=String,Description=\"UniProt accession of the protein scored by AlphaMissense\">",
+    ),
+];
+
+/// Errores al anotar un VCF.
+#[derive(Debug)]
+pub enum VcfError {
+    /// No se pudo leer el VCF o escribir el resultado.
+    Io(io::Error),
+    /// La línea no tiene las 8 columnas obligatorias o su posición no es un número.
+    Malformed { line: usize, text: String },
+    /// El encabezado del VCF indica un ensamble distinto al que se pidió.
+    DeclaredAssembly {
+        declared: Assembly,
+        requested: Assembly,
+    },
+    /// No se pudo consultar el índice genómico.
+    Genome(GenomicError),
+}
+
+impl std::fmt::Display for VcfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VcfError::Io(e) => write!(f, "Error al leer o escribir el VCF: {}", e),
+            VcfError::Malformed { line, text } => {
+                write!(f, "Línea {} del VCF inválida: {:?}", line, text)
+            }
+            VcfError::DeclaredAssembly {
+                declared,
+                requested,
+            } => write!(
+                f,
+                "El encabezado del VCF indica que sus coordenadas son de {}, no de {}",
+                declared, requested
+            ),
+            VcfError::Genome(e) => write!(f, "Error en el índice genómico: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VcfError {}
+
+impl From<io::Error> for VcfError {
+    fn from(e: io::Error) -> Self {
+        VcfError::Io(e)
+    }
+}
+
+impl From<GenomicError> for VcfError {
+    fn from(e: GenomicError) -> Self {
+        VcfError::Genome(e)
+    }
+}
+
+/// Pares `clave=valor` del valor de una línea estructurada del encabezado, como
+/// `<ID=DP,Number=1,Description="Profundidad, en lecturas">`. Las comas entre comillas no
+/// separan pares.
+fn structured(value: &str) -> Vec<(&str, &str)> {
+    let value = value.trim();
+    let Some(inner) = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) else {
+        return Vec::new();
+    };
+
+    let mut fields = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&inner[start..]);
+
+    fields
+        .into_iter()
+        .filter_map(|field| field.split_once('='))
+        .collect()
+}
+
+/// Ensamble que declara una línea del encabezado: el valor de `##reference` o
+/// `##assembly`, como `##reference=GRCh37`, o la clave `assembly` de un contig, como
+/// `##contig=<ID=1,length=249250621,assembly=b37>`. El valor se compara por palabras
+/// completas, así que `human_g1k_v37.fasta` o un `md5` no cuentan.
+fn declared_assembly(line: &str) -> Option<Assembly> {
+    let (key, value) = line.strip_prefix("##")?.split_once('=')?;
+    let value = match key {
+        "reference" | "assembly" => value,
+        "contig" => structured(value)
+            .into_iter()
+            .find_map(|(key, value)| (key == "assembly").then_some(value))?,
+        _ => return None,
+    };
+
+    value
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .find_map(|token| match token.to_lowercase().as_str() {
+            "grch37" | "hg19" | "b37" | "hs37" | "hs37d5" => Some(Assembly::Hg19),
+            "grch38" | "hg38" | "hs38" | "hs38dh" => Some(Assembly::Hg38),
+            _ => None,
+        })
+}
+
+/// Indica si la línea del encabezado describe uno de los campos de [`FIELDS`], que se
+/// reemplazan. Se compara el `ID` completo: `AM_SCORE_X` no es `AM_SCORE`.
+fn replaced_field(line: &str) -> bool {
+    line.strip_prefix("##INFO=").is_some_and(|value| {
+        structured(value)
+            .into_iter()
+            .any(|(key, id)| key == "ID" && FIELDS.iter().any(|(field, _)| *field == id))
+    })
+}
+
+/// Cuántas variantes se leyeron y cuántas se anotaron.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub variants: usize,
+    pub annotated: usize,
+}
+
+/// Lee un VCF con coordenadas de `assembly`, comprimido con bgzip o no, y lo escribe sin
+/// comprimir en `output` con las predicciones de AlphaMissense en los campos INFO de
+/// [`FIELDS`]. Si el VCF ya tenía esos campos, se reemplazan. Si su encabezado declara
+/// otro ensamble, no se anota.
+pub fn annotate(
+    genome: &Genome,
+    assembly: Assembly,
+    vcf: impl Read + Send + 'static,
+    mut output: impl Write,
+) -> Result<Summary, VcfError> {
+    let mut summary = Summary::default();
+    let mut header_written = false;
+
+    for (number, line) in input::decompress(vcf)?.lines().enumerate() {
+        let line = line?;
+
+        if line.starts_with("##") {
+            match declared_assembly(&line) {
+                Some(declared) if declared != assembly => {
+                    return Err(VcfError::DeclaredAssembly {
+                        declared,
+                        requested: assembly,
+                    })
+                }
+                _ => {}
+            }
+            if !replaced_field(&line) {
+                writeln!(output, "{}", line)?;
+            }
+            continue;
+        }
+        if !header_written {
+            for (_, header) in FIELDS {
+                writeln!(output, "{}", header)?;
+            }
+            header_written = true;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            writeln!(output, "{}", line)?;
+            continue;
+        }
+
+        let malformed = || VcfError::Malformed {
+            line: number + 1,
+            text: line.clone(),
+        };
+        let mut columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 8 {
+            return Err(malformed());
+        }
+        let position = columns[1].parse().map_err(|_| malformed())?;
+
+        let annotations = columns[4]
+            .split(',')
+            .map(|alternate| genome.lookup(assembly, columns[0], position, columns[3], alternate))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut info: Vec<String> = columns[7]
+            .split(';')
+            .filter(|entry| {
+                let key = entry.split('=').next().unwrap_or_default();
+                *entry != "." && FIELDS.iter().all(|(field, _)| *field != key)
+            })
+            .map(str::to_owned)
+            .collect();
+
+        summary.variants += 1;
+        if annotations.iter().any(|alternate| !alternate.is_empty()) {
+            summary.annotated += 1;
+            for (field, _) in FIELDS {
+                info.push(format!("{}={}", field, values(field, &annotations)));
+            }
+        }
+
+        let info = match info.is_empty() {
+            true => ".".to_owned(),
+            false => info.join(";"),
+        };
+        columns[7] = &info;
+        writeln!(output, "{}", columns.join("\t"))?;
+    }
+
+    output.flush()?;
+
+    Ok(summary)
+}
+
+/// Valor del campo `field` para todos los alelos alternativos.
+fn values(field: &str, annotations: &[Vec<Annotation<'_>>]) -> String {
+    annotations
+        .iter()
+        .map(|alternate| {
+            if alternate.is_empty() {
+                return ".".to_owned();
+            }
+
+            alternate
+                .iter()
+                .map(|annotation| match field {
+                    "AM_SCORE" => annotation
+                        .class
+                        .score()
+                        .map_or(".".to_owned(), |score| score.to_string()),
+                    "AM_CLASS" => annotation.class.name().to_owned(),
+                    "AM_PROTEIN_VARIANT" => annotation.variation.to_string(),
+                    _ => annotation.uniprot_id.to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join("|")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genomic::{
+        tests::{synthetic_table, ROWS},
+        GenomicIndex,
+    };
+
+    #[test]
+    fn annotates_vcf_info() {
+        let input = synthetic_table("vcf", &ROWS);
+        let output = input.with_extension("cdv");
+        GenomicIndex::build(&input, &output, Assembly::Hg38, false).unwrap();
+        let genome = Genome::new(Some(GenomicIndex::load(&output).unwrap()), None, None);
+
+        let vcf = "##fileformat=VCFv4.2\n\
+            ##INFO=<ID=AM_SCORE,Number=A,Type=Float,Description=\"Anterior\">\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+            17\t7675088\trs28934578\tC\tT,A,G\t.\tPASS\tDP=10;AM_SCORE=0.1\n\
+            chr17\t7675089\t.\tG\tA\t.\tPASS\t.\n\
+            chr1\t100\t.\tA\tG\t.\tPASS\tDP=3\n";
+
+        let mut annotated = Vec::new();
+        let summary = annotate(
+            &genome,
+            Assembly::Hg38,
+            io::Cursor::new(vcf.as_bytes().to_vec()),
+            &mut annotated,
+        )
+        .unwrap();
+        let annotated = String::from_utf8(annotated).unwrap();
+        let lines: Vec<_> = annotated.lines().collect();
+
+        assert_eq!(
+            summary,
+            Summary {
+                variants: 3,
+                annotated: 2
+            }
+        );
+        assert_eq!(lines[0], "##fileformat=VCFv4.2");
+        assert_eq!(&lines[1..5], FIELDS.map(|(_, header)| header));
+        assert!(lines[5].starts_with("#CHROM"));
+        assert_eq!(
+            lines[6].split('\t').nth(7).unwrap(),
+            "DP=10;AM_SCORE=0.9977,0.9912,.;AM_CLASS=pathogenic,pathogenic,.;\
+             AM_PROTEIN_VARIANT=R175H,R175L,.;AM_UNIPROT=P04637,P04637,."
+        );
+        assert_eq!(
+            lines[7].split('\t').nth(7).unwrap(),
+            "AM_SCORE=0.3001;AM_CLASS=benign;AM_PROTEIN_VARIANT=R175C;AM_UNIPROT=P04637"
+        );
+        assert_eq!(lines[8], "chr1\t100\t.\tA\tG\t.\tPASS\tDP=3");
+
+        let bad = "#CHROM\tPOS\n17\tx\t.\tC\tT\t.\t.\t.\n";
+        let read = |vcf: &str| io::Cursor::new(vcf.as_bytes().to_vec());
+        assert!(matches!(
+            annotate(&genome, Assembly::Hg38, read(bad), io::sink()),
+            Err(VcfError::Malformed { line: 2, .. })
+        ));
+        assert!(matches!(
+            annotate(&genome, Assembly::Hg19, read(vcf), io::sink()),
+            Err(VcfError::Genome(GenomicError::Unavailable {
+                assembly: Assembly::Hg19
+            }))
+        ));
+        assert!(matches!(
+            annotate(
+                &genome,
+                Assembly::Hg38,
+                read("##reference=GRCh37\n"),
+                io::sink()
+            ),
+            Err(VcfError::DeclaredAssembly {
+                declared: Assembly::Hg19,
+                requested: Assembly::Hg38
+            })
+        ));
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn header_lines_are_read_by_key() {
+        assert_eq!(
+            declared_assembly("##reference=GRCh37"),
+            Some(Assembly::Hg19)
+        );
+        assert_eq!(
+            declared_assembly("##reference=file:///ref/GRCh38_full_analysis_set.fa"),
+            Some(Assembly::Hg38)
+        );
+        assert_eq!(
+            declared_assembly("##contig=<ID=1,length=249250621,assembly=b37>"),
+            Some(Assembly::Hg19)
+        );
+        assert_eq!(
+            declared_assembly(
+                "##contig=<ID=chrb37,length=10,md5=00b37000hs3700000000000000000000>"
+            ),
+            None
+        );
+        assert_eq!(declared_assembly("##source=hg19-liftover"), None);
+
+        assert!(replaced_field(
+            "##INFO=<ID=AM_SCORE,Number=A,Type=Float,Description=\"Anterior, sin uso\">"
+        ));
+        assert!(!replaced_field(
+            "##INFO=<ID=AM_SCORE_X,Number=A,Type=Float>"
+        ));
+        assert!(!replaced_field(
+            "##FORMAT=<ID=AM_SCORE,Number=A,Type=Float>"
+        ));
+    }
+}